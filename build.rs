@@ -4,7 +4,7 @@ fn main() {
     let mut version = execute("git", &["describe", "--tags", "--dirty"]);
 
     if version.is_empty() {
-        version = format!("v{}", env!("CARGO_PKG_VERSION").to_string())
+        version = format!("v{}", env!("CARGO_PKG_VERSION"))
     }
 
     println!("cargo:rustc-env=VERSION={}", version);
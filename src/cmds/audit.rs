@@ -0,0 +1,239 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use clap::Args;
+use lazy_static::lazy_static;
+use regex::Regex;
+use tracing_log::log;
+
+use crate::cmds::CliCommand;
+use crate::git::{GitCli, GitCommit, GitOperationTrait};
+use crate::Cli;
+
+lazy_static! {
+    static ref SIGNED_OFF_BY_REG: Regex =
+        Regex::new(r"(?m)^Signed-off-by:\s*(.+?)\s*<([^>]+)>\s*$").unwrap();
+}
+
+#[derive(Args)]
+#[command(about = "Audit the DCO sign-off and commit policies of repos between tags")]
+pub struct AuditArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repos")]
+    repos: Vec<String>,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, help = "Tag")]
+    tag: String,
+
+    #[arg(long, help = "Previous tag")]
+    prev_tag: Option<String>,
+
+    #[arg(long, help = "Audit from the last public release, not pre release")]
+    public: bool,
+
+    #[arg(long, help = "Allowed domains of author emails")]
+    allowed_email_domains: Vec<String>,
+
+    #[arg(long, help = "Regex the commit subject should match")]
+    subject_pattern: Option<String>,
+
+    #[arg(
+        long,
+        default_values = ["renovate[bot]", "dependabot[bot]"],
+        help = "Authors whose commits are not audited"
+    )]
+    skip_authors: Vec<String>,
+}
+
+/// Policies a commit should follow.
+#[derive(Clone, Default)]
+struct AuditRules {
+    allowed_email_domains: Vec<String>,
+    subject_pattern: Option<Regex>,
+}
+
+impl AuditRules {
+    /// Returns the violations of the commit.
+    fn check(&self, commit: &GitCommit) -> Vec<String> {
+        let mut violations = vec![];
+
+        let sign_offs: Vec<(String, String)> = SIGNED_OFF_BY_REG
+            .captures_iter(&commit.message)
+            .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+            .collect();
+        if sign_offs.is_empty() {
+            violations.push("missing Signed-off-by".to_string());
+        } else if !sign_offs
+            .iter()
+            .any(|(_, email)| email.eq_ignore_ascii_case(&commit.author_email))
+        {
+            violations.push(format!(
+                "Signed-off-by ({}) does not match the author <{}>",
+                sign_offs
+                    .iter()
+                    .map(|(name, email)| format!("{name} <{email}>"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                commit.author_email
+            ));
+        }
+
+        if !self.allowed_email_domains.is_empty() {
+            let domain = commit
+                .author_email
+                .rsplit_once('@')
+                .map(|(_, domain)| domain)
+                .unwrap_or_default();
+
+            if !self
+                .allowed_email_domains
+                .iter()
+                .any(|it| it.eq_ignore_ascii_case(domain))
+            {
+                violations.push(format!("author email domain {} is not allowed", domain));
+            }
+        }
+
+        if let Some(pattern) = &self.subject_pattern {
+            let subject = commit.message.lines().next().unwrap_or_default();
+
+            if !pattern.is_match(subject) {
+                violations.push(format!("subject does not match {}", pattern.as_str()));
+            }
+        }
+
+        violations
+    }
+}
+
+#[async_trait]
+impl CliCommand for AuditArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let rules = AuditRules {
+            allowed_email_domains: self.allowed_email_domains.clone(),
+            subject_pattern: self
+                .subject_pattern
+                .as_ref()
+                .map(|it| Regex::new(it))
+                .transpose()?,
+        };
+
+        let mut report = format!("## Commit Audit {}\n", self.tag);
+        let mut violation_count = 0;
+
+        for repo in &self.repos {
+            let git = GitCli::new(self.owner.clone(), repo.clone());
+            git.clone_repo(&self.branch)?;
+
+            let prev_tag = match &self.prev_tag {
+                Some(prev_tag) => prev_tag.clone(),
+                None => git.previous_tag(&self.tag, self.public)?,
+            };
+
+            log::info!(
+                "Auditing commits of {} between {} and {}",
+                git.repo.repo_ref(),
+                prev_tag,
+                self.tag
+            );
+
+            let mut violations = String::new();
+            for commit in git.commits(&format!("{}..{}", prev_tag, self.tag))? {
+                if self
+                    .skip_authors
+                    .iter()
+                    .any(|it| it.eq_ignore_ascii_case(&commit.author_name))
+                {
+                    continue;
+                }
+
+                for violation in rules.check(&commit) {
+                    violation_count += 1;
+                    violations += &format!(
+                        "- [{}](https://github.com/{}/commit/{}) {} by {} <{}>: {}\n",
+                        &commit.sha[0..8],
+                        git.repo.repo_ref(),
+                        commit.sha,
+                        commit.message.lines().next().unwrap_or_default(),
+                        commit.author_name,
+                        commit.author_email,
+                        violation
+                    );
+                }
+            }
+
+            report += &format!(
+                "\n### {} ({}..{})\n{}",
+                git.repo.repo_ref(),
+                prev_tag,
+                self.tag,
+                if violations.is_empty() {
+                    "No violations\n".to_string()
+                } else {
+                    violations
+                }
+            );
+        }
+
+        println!("{}", report);
+
+        if violation_count > 0 {
+            return Err(anyhow!(
+                "found {} commit policy violations",
+                violation_count
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_rules_check() -> anyhow::Result<()> {
+        let rules = AuditRules {
+            allowed_email_domains: vec!["suse.com".to_string()],
+            subject_pattern: Some(Regex::new(r"^\w+(\(.+\))?!?: .+")?),
+        };
+        let commit = |email: &str, message: &str| GitCommit {
+            sha: "0123456789abcdef".to_string(),
+            author_name: "Dev".to_string(),
+            author_email: email.to_string(),
+            message: message.to_string(),
+        };
+
+        let data = vec![
+            (
+                commit("dev@suse.com", "fix: foo\n\nSigned-off-by: Dev <dev@suse.com>"),
+                vec![],
+            ),
+            (
+                commit("dev@suse.com", "Fix foo"),
+                vec![
+                    "missing Signed-off-by",
+                    r"subject does not match ^\w+(\(.+\))?!?: .+",
+                ],
+            ),
+            (
+                commit("dev@example.com", "fix: foo\n\nSigned-off-by: Dev <dev@suse.com>"),
+                vec![
+                    "Signed-off-by (Dev <dev@suse.com>) does not match the author <dev@example.com>",
+                    "author email domain example.com is not allowed",
+                ],
+            ),
+        ];
+
+        for (commit, expected) in data {
+            assert_eq!(rules.check(&commit), expected);
+        }
+
+        Ok(())
+    }
+}
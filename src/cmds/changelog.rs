@@ -1,15 +1,38 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+
+use anyhow::anyhow;
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
-use clap::Args;
+use clap::{Args, ValueEnum};
+use indexmap::{indexmap, IndexMap, IndexSet};
 use indoc::formatdoc;
+use lazy_static::lazy_static;
 use octocrab::models::commits::Commit;
+use regex::Regex;
 use tracing_log::log;
 
-use crate::cmds::CliCommand;
+use crate::cmds::pr::pull_request_options;
+use crate::cmds::{run_repo_tasks, CliCommand};
+use crate::config::Config;
 use crate::git::{GitCli, GitOperationTrait};
-use crate::github::github_client;
+use crate::github::{
+    compare_commits, get_file, github_client, is_ancestor, list_tags, GithubCli,
+    GithubOperationTrait,
+};
+use crate::gomod::{diff_requires, ModuleChange};
+use crate::issue::{parse_issue_refs, resolve_issue, IssueRef, LinkedIssue};
 use crate::{cmd, Cli};
 
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum RepoOrder {
+    /// Keep the order of --repos
+    #[default]
+    Input,
+    /// Sort repos alphabetically
+    Alphabetical,
+}
+
 #[derive(Args)]
 #[command(about = "Create a Changelog for repos between tags")]
 pub struct ChangelogArgs {
@@ -42,62 +65,590 @@ pub struct ChangelogArgs {
 
     #[arg(long, help = "Fold changelog of each repo")]
     markdown_folding: bool,
+
+    #[arg(long, value_enum, default_value_t, help = "Order of repo sections")]
+    order: RepoOrder,
+
+    #[arg(
+        long,
+        help = "Fail as soon as the changelog of any repo fails, instead of after printing the others"
+    )]
+    strict: bool,
+
+    #[arg(
+        long,
+        requires = "tag",
+        help = "Changelog file of each repo to write the changes into (e.g. CHANGELOG.md)"
+    )]
+    write: Option<String>,
+
+    #[arg(
+        long,
+        requires = "write",
+        help = "Create PRs for the written changelog files"
+    )]
+    pr: bool,
+
+    #[arg(long, help = "Only keep commits touching the paths (support glob)")]
+    include_paths: Vec<String>,
+
+    #[arg(long, help = "Drop commits only touching the paths (support glob)")]
+    exclude_paths: Vec<String>,
+
+    #[arg(long, help = "Only keep commits whose subject matches the regex")]
+    include_subjects: Vec<String>,
+
+    #[arg(long, help = "Drop commits whose subject matches the regex")]
+    exclude_subjects: Vec<String>,
+
+    #[arg(long, help = "Only keep commits of the authors (login, name or email)")]
+    authors: Vec<String>,
+
+    #[arg(long, help = "Drop commits of the authors (login, name or email)")]
+    exclude_authors: Vec<String>,
+
+    #[arg(long, help = "Only follow the first parent of merge commits")]
+    first_parent: bool,
+
+    #[arg(
+        long,
+        default_values = ["renovate[bot]", "dependabot[bot]"],
+        help = "Bot authors whose commits are summarized as dependency updates"
+    )]
+    bots: Vec<String>,
+
+    #[arg(long, help = "Diff the direct requirements of go.mod between tags")]
+    go_mod_diff: bool,
+
+    #[arg(long, help = "Link the issues referenced by commit messages")]
+    link_issues: bool,
+
+    #[arg(
+        long,
+        help = "Group commits by the referenced issues, implies --link-issues"
+    )]
+    group_by_issue: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["write", "include_paths", "exclude_paths"],
+        help = "Use the GitHub compare API instead of cloning repos"
+    )]
+    no_clone: bool,
+}
+
+/// Options shared by the report of each repo.
+#[derive(Clone)]
+pub(crate) struct RepoReportOptions {
+    pub(crate) branch: String,
+    pub(crate) tag: String,
+    pub(crate) prev_tag: Option<String>,
+    pub(crate) since_days: i64,
+    pub(crate) is_public: bool,
+    pub(crate) filters: CommitFilters,
+    pub(crate) bots: Vec<String>,
+    pub(crate) is_go_mod_diff: bool,
+    pub(crate) is_link_issues: bool,
+    pub(crate) is_no_clone: bool,
+}
+
+impl RepoReportOptions {
+    /// Creates the options of a report of all the commits between the tags, without filters and
+    /// extra sections.
+    pub(crate) fn new(
+        branch: String,
+        tag: Option<String>,
+        prev_tag: Option<String>,
+        since_days: i64,
+        is_public: bool,
+        bots: Vec<String>,
+    ) -> Self {
+        Self {
+            branch,
+            tag: tag.unwrap_or_default(),
+            prev_tag,
+            since_days,
+            is_public,
+            filters: Default::default(),
+            bots,
+            is_go_mod_diff: false,
+            is_link_issues: false,
+            is_no_clone: false,
+        }
+    }
+}
+
+/// Filters to drop commits from a changelog.
+#[derive(Clone, Default)]
+pub(crate) struct CommitFilters {
+    pub(crate) include_paths: Vec<String>,
+    pub(crate) exclude_paths: Vec<String>,
+    pub(crate) include_subjects: Vec<Regex>,
+    pub(crate) exclude_subjects: Vec<Regex>,
+    pub(crate) authors: Vec<String>,
+    pub(crate) exclude_authors: Vec<String>,
+    pub(crate) first_parent: bool,
+}
+
+impl CommitFilters {
+    fn pathspecs(&self) -> Vec<String> {
+        let mut pathspecs: Vec<String> = self
+            .include_paths
+            .iter()
+            .map(|it| format!(":(glob){it}"))
+            .collect();
+
+        pathspecs.extend(
+            self.exclude_paths
+                .iter()
+                .map(|it| format!(":(glob,exclude){it}")),
+        );
+
+        pathspecs
+    }
+
+    fn is_subject_kept(&self, subject: &str) -> bool {
+        (self.include_subjects.is_empty()
+            || self.include_subjects.iter().any(|it| it.is_match(subject)))
+            && !self.exclude_subjects.iter().any(|it| it.is_match(subject))
+    }
+
+    fn is_author_kept(&self, commit: &ChangelogCommit) -> bool {
+        (self.authors.is_empty() || self.authors.iter().any(|it| commit.is_authored_by(it)))
+            && !self
+                .exclude_authors
+                .iter()
+                .any(|it| commit.is_authored_by(it))
+    }
+
+    /// Drops the commits not passing the filters, and returns the number of dropped commits by filter.
+    pub(crate) fn apply(
+        &self,
+        git: &impl GitOperationTrait,
+        range: &str,
+        commits: &mut Vec<ChangelogCommit>,
+    ) -> anyhow::Result<IndexMap<String, usize>> {
+        self.apply_with(
+            |first_parent, pathspecs| git.rev_list(range, first_parent, pathspecs),
+            commits,
+        )
+    }
+
+    /// Same as `apply`, but lists the commits of the range by `rev_list(first_parent, pathspecs)`.
+    pub(crate) fn apply_with(
+        &self,
+        rev_list: impl Fn(bool, &[String]) -> anyhow::Result<Vec<String>>,
+        commits: &mut Vec<ChangelogCommit>,
+    ) -> anyhow::Result<IndexMap<String, usize>> {
+        let mut dropped = indexmap! {};
+        let mut retain = |name: &str,
+                          commits: &mut Vec<ChangelogCommit>,
+                          f: &dyn Fn(&ChangelogCommit) -> bool| {
+            let count = commits.len();
+            commits.retain(|it| f(it));
+            dropped.insert(name.to_string(), count - commits.len());
+        };
+
+        if self.first_parent {
+            let shas: HashSet<String> = rev_list(true, &[])?.into_iter().collect();
+            retain("first-parent", commits, &|it| shas.contains(&it.sha));
+        }
+
+        let pathspecs = self.pathspecs();
+        if !pathspecs.is_empty() {
+            let shas: HashSet<String> = rev_list(self.first_parent, &pathspecs)?
+                .into_iter()
+                .collect();
+            retain("paths", commits, &|it| shas.contains(&it.sha));
+        }
+
+        if !self.include_subjects.is_empty() || !self.exclude_subjects.is_empty() {
+            retain("subjects", commits, &|it| {
+                self.is_subject_kept(it.subject())
+            });
+        }
+
+        if !self.authors.is_empty() || !self.exclude_authors.is_empty() {
+            retain("authors", commits, &|it| self.is_author_kept(it));
+        }
+
+        Ok(dropped)
+    }
+}
+
+/// A commit listed in a changelog.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ChangelogCommit {
+    pub(crate) sha: String,
+    pub(crate) message: String,
+    pub(crate) html_url: String,
+    pub(crate) author_login: Option<String>,
+    pub(crate) author_name: String,
+    pub(crate) author_email: String,
+    /// The issues referenced by the message, resolved if issue linking is enabled.
+    pub(crate) issues: Vec<LinkedIssue>,
+    /// The commit reverted by this commit if it is from before the changelog range.
+    pub(crate) reverted_sha: Option<String>,
+}
+
+impl ChangelogCommit {
+    pub(crate) fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+
+    pub(crate) fn short_sha(&self) -> &str {
+        &self.sha[0..8.min(self.sha.len())]
+    }
+
+    /// The GitHub login if the author is linked to an account, otherwise the git author name.
+    pub(crate) fn author(&self) -> String {
+        match &self.author_login {
+            Some(login) => format!("@{login}"),
+            None => self.author_name.clone(),
+        }
+    }
+
+    /// Whether the author has the login, name or email.
+    pub(crate) fn is_authored_by(&self, author: &str) -> bool {
+        let author = author.trim_start_matches('@');
+
+        self.author_login
+            .as_ref()
+            .is_some_and(|it| it.eq_ignore_ascii_case(author))
+            || self.author_name.eq_ignore_ascii_case(author)
+            || self.author_email.eq_ignore_ascii_case(author)
+    }
+}
+
+impl From<&Commit> for ChangelogCommit {
+    fn from(commit: &Commit) -> Self {
+        let author = commit.commit.author.as_ref();
+
+        Self {
+            sha: commit.sha.clone(),
+            message: commit.commit.message.clone(),
+            html_url: commit.html_url.clone(),
+            author_login: commit.author.as_ref().map(|it| it.login.clone()),
+            author_name: author.and_then(|it| it.name.clone()).unwrap_or_default(),
+            author_email: author.and_then(|it| it.email.clone()).unwrap_or_default(),
+            issues: vec![],
+            reverted_sha: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl ChangelogCommit {
+    /// Creates a commit of longhorn/longhorn-manager for tests.
+    pub(crate) fn for_test(sha: &str, message: &str, login: Option<&str>) -> Self {
+        Self {
+            sha: sha.to_string(),
+            message: message.to_string(),
+            html_url: format!("https://github.com/longhorn/longhorn-manager/commit/{sha}"),
+            author_login: login.map(|it| it.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// A dependency bumped by a bot commit.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DependencyUpdate {
+    pub(crate) dependency: String,
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+    pub(crate) commit: ChangelogCommit,
+}
+
+impl DependencyUpdate {
+    /// Parses the dependency and versions from the subject of a renovate or dependabot commit.
+    pub(crate) fn parse(commit: ChangelogCommit) -> Self {
+        lazy_static! {
+            static ref BUMP_REG: Regex =
+                Regex::new(r"(?i)\bbump (\S+) from (\S+) to (\S+)").unwrap();
+            static ref UPDATE_REG: Regex = Regex::new(
+                r"(?i)\bupdate (?:module |dependency |)(\S+)(?: docker tag| digest)? to (\S+)"
+            )
+            .unwrap();
+            static ref PREFIX_REG: Regex = Regex::new(r"^\w+(\([^)]*\))?!?:\s*").unwrap();
+        }
+
+        let subject = commit.subject().to_string();
+
+        if let Some(caps) = BUMP_REG.captures(&subject) {
+            return Self {
+                dependency: caps[1].to_string(),
+                from: Some(caps[2].to_string()),
+                to: Some(caps[3].to_string()),
+                commit,
+            };
+        }
+
+        if let Some(caps) = UPDATE_REG.captures(&subject) {
+            return Self {
+                dependency: caps[1].to_string(),
+                from: None,
+                to: Some(caps[2].to_string()),
+                commit,
+            };
+        }
+
+        Self {
+            dependency: PREFIX_REG.replace(&subject, "").to_string(),
+            from: None,
+            to: None,
+            commit,
+        }
+    }
+}
+
+/// The changes of a repo between the previous tag and the tag (or the branch head).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RepoChangelog {
+    pub(crate) repo_ref: String,
+    pub(crate) branch: String,
+    pub(crate) tag: String,
+    pub(crate) prev_tag: String,
+    pub(crate) commits: Vec<ChangelogCommit>,
+    /// The commits of bots, summarized as dependency updates.
+    pub(crate) dependency_updates: Vec<DependencyUpdate>,
+    /// The go.mod requirement changes.
+    pub(crate) module_changes: Vec<ModuleChange>,
+    /// The number of commits dropped by each filter.
+    pub(crate) dropped: IndexMap<String, usize>,
+}
+
+impl RepoChangelog {
+    pub(crate) fn has_changes(&self) -> bool {
+        !self.commits.is_empty()
+            || !self.dependency_updates.is_empty()
+            || !self.module_changes.is_empty()
+    }
+
+    /// The numbers of the non-empty kinds of changes, e.g. "2 commits, 1 dependency update".
+    pub(crate) fn change_counts(&self) -> String {
+        [
+            (self.commits.len(), "commit"),
+            (self.dependency_updates.len(), "dependency update"),
+            (self.module_changes.len(), "go.mod change"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, noun)| pluralize(count, noun))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+
+    pub(crate) fn contributors(&self) -> IndexSet<String> {
+        self.commits.iter().map(|it| it.author()).collect()
+    }
+
+    pub(crate) fn compare_url(&self) -> Option<String> {
+        if self.prev_tag.is_empty() {
+            return None;
+        }
+
+        let head = if self.tag.is_empty() {
+            &self.branch
+        } else {
+            &self.tag
+        };
+
+        Some(format!(
+            "https://github.com/{}/compare/{}...{}",
+            self.repo_ref, self.prev_tag, head
+        ))
+    }
+}
+
+/// A repo whose changelog could not be generated.
+#[derive(Clone, Debug)]
+pub(crate) struct RepoError {
+    pub(crate) repo_ref: String,
+    pub(crate) reason: String,
 }
 
 #[async_trait]
 impl CliCommand for ChangelogArgs {
-    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
-        let mut changelog = String::new();
-        let mut task_joiner = tokio::task::JoinSet::new();
-
-        for repo in &self.repos {
-            task_joiner.spawn(generate_repo_report(
-                self.owner.clone(),
-                repo.clone(),
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        let opts = RepoReportOptions {
+            filters: CommitFilters {
+                include_paths: self.include_paths.clone(),
+                exclude_paths: self.exclude_paths.clone(),
+                include_subjects: compile_regexes(&self.include_subjects)?,
+                exclude_subjects: compile_regexes(&self.exclude_subjects)?,
+                authors: self.authors.clone(),
+                exclude_authors: self.exclude_authors.clone(),
+                first_parent: self.first_parent,
+            },
+            is_go_mod_diff: self.go_mod_diff,
+            is_link_issues: self.link_issues || self.group_by_issue,
+            is_no_clone: self.no_clone,
+            ..RepoReportOptions::new(
                 self.branch.clone(),
-                self.tag.clone().unwrap_or_default(),
+                self.tag.clone(),
                 self.prev_tag.clone(),
-                self.find_prev_tag,
                 self.since_days,
                 self.public,
-                self.markdown_folding,
-            ));
+                self.bots.clone(),
+            )
+        };
+
+        let results = run_repo_tasks(&self.owner, &self.repos, |owner, repo| {
+            generate_repo_report(owner, repo, opts.clone())
+        })
+        .await?;
+
+        let mut changelogs = vec![];
+        let mut errors = vec![];
+        for (repo, result) in self.repos.iter().zip(results) {
+            let repo_ref = format!("{}/{}", self.owner, repo);
+
+            match result {
+                Ok(changelog) => changelogs.push(changelog),
+                Err(err) => {
+                    if self.strict {
+                        return Err(
+                            err.context(format!("failed to create changelog of {repo_ref}"))
+                        );
+                    }
+
+                    log::warn!("Failed to create changelog of {}: {:#}", repo_ref, err);
+                    errors.push(RepoError {
+                        repo_ref,
+                        reason: format!("{:#}", err),
+                    });
+                }
+            }
         }
 
-        while let Some(res) = task_joiner.join_next().await {
-            let str = res??;
-            changelog += &str;
+        if self.order == RepoOrder::Alphabetical {
+            changelogs.sort_by(|a, b| a.repo_ref.cmp(&b.repo_ref));
+            errors.sort_by(|a, b| a.repo_ref.cmp(&b.repo_ref));
         }
 
-        println!("{}", changelog);
+        let mut output = render_changelog(&changelogs, &errors, self.markdown_folding);
+        if self.group_by_issue {
+            output += &render_issue_groups(&changelogs);
+        }
+
+        println!("{}", output);
+
+        if let Some(file) = &self.write {
+            let config = Config::load(cli.config.as_deref())?;
+            self.write_changelog_files(file, &changelogs, &config)?;
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "failed to create changelogs of {}",
+                errors
+                    .iter()
+                    .map(|it| it.repo_ref.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
 
         Ok(())
     }
 }
 
-async fn generate_repo_report(
+impl ChangelogArgs {
+    fn write_changelog_files(
+        &self,
+        file: &str,
+        changelogs: &[RepoChangelog],
+        config: &Config,
+    ) -> anyhow::Result<()> {
+        let tag = self.tag.clone().unwrap_or_default();
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+
+        for changelog in changelogs {
+            if !changelog.has_changes() {
+                log::info!(
+                    "No changes in {}, skipped writing {}",
+                    changelog.repo_ref,
+                    file
+                );
+                continue;
+            }
+
+            let (owner, repo) = changelog
+                .repo_ref
+                .split_once('/')
+                .ok_or_else(|| anyhow!("invalid repo {}", changelog.repo_ref))?;
+            let git = GitCli::new(owner.to_string(), repo.to_string());
+            let path = git.repo.repo_dir_path().join(file);
+
+            let content = if path.exists() {
+                fs::read_to_string(&path)?
+            } else {
+                String::from("# Changelog\n")
+            };
+
+            let entries = render_changes(changelog);
+            let Some(content) = update_changelog_file(&content, &tag, &date, entries.trim_start())
+            else {
+                log::warn!("{:?} already has a section for {}, skipped", path, tag);
+                continue;
+            };
+
+            log::info!(
+                "Writing changelog of {} into {:?}",
+                changelog.repo_ref,
+                path
+            );
+            fs::write(&path, content)?;
+
+            if self.pr {
+                let gh_client = GithubCli::new(owner.to_string(), repo.to_string());
+                let id = gh_client.create_pr(
+                    &format!("docs: update {} for {}", file, tag),
+                    &tag,
+                    &self.branch,
+                    &format!("changelog-{}", tag),
+                    &pull_request_options(config, owner, repo, &tag, None),
+                )?;
+                log::info!("Created PR {} for {}", id.trim(), changelog.repo_ref);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) async fn generate_repo_report(
     owner: String,
     repo: String,
-    branch: String,
-    tag: String,
-    prev_tag: Option<String>,
-    is_find_prev_tag: bool,
-    since_days: i64,
-    is_public: bool,
-    is_markdown_folding: bool,
-) -> anyhow::Result<String> {
+    opts: RepoReportOptions,
+) -> anyhow::Result<RepoChangelog> {
+    if opts.is_no_clone {
+        return generate_repo_report_by_api(owner, repo, opts).await;
+    }
+
+    let RepoReportOptions {
+        branch,
+        tag,
+        prev_tag,
+        since_days,
+        is_public,
+        filters,
+        bots,
+        is_go_mod_diff,
+        is_link_issues,
+        ..
+    } = opts;
+
     let git = GitCli::new(owner.clone(), repo.clone());
     git.clone_repo(&branch)?;
 
-    let mut changelog = String::new();
     let mut prev_tag = prev_tag.unwrap_or_default();
 
     if prev_tag.is_empty() {
         match git.previous_tag(&tag, is_public) {
             Ok(s) => {
-                log::info!(
-                    "Found previous tag: {prev_tag}, owner: {owner} repo: {repo} branch: {branch}"
-                );
+                log::info!("Found previous tag: {s}, owner: {owner} repo: {repo} branch: {branch}");
                 prev_tag = s;
             }
             Err(err) => {
@@ -112,6 +663,17 @@ async fn generate_repo_report(
         anyhow::Ok(String::new())
     })?;
 
+    let mut changelog = RepoChangelog {
+        repo_ref: git.repo.repo_ref().clone(),
+        branch: branch.clone(),
+        tag: tag.clone(),
+        prev_tag: prev_tag.clone(),
+        commits: vec![],
+        dependency_updates: vec![],
+        module_changes: vec![],
+        dropped: indexmap! {},
+    };
+
     if !prev_tag.is_empty() && !prev_tag_hash.is_empty() {
         let output = cmd!(
             "git",
@@ -164,22 +726,11 @@ async fn generate_repo_report(
                             }
                         }
 
-                        if !is_find_prev_tag || tag_found {
-                            if tag_found {
-                                if commit.sha.starts_with(&prev_tag_hash) {
-                                    break 'outer;
-                                }
-                            }
-
-                            changelog += &formatdoc! {"
-                                    - {} [{}]({}) {}
-                                    ",
-                                commit.commit.message.lines().next().unwrap(),
-                                &commit.sha[0..8],
-                                commit.html_url,
-                                commit.author.as_ref().map(|it| String::from("by @") + it.login.as_str()).unwrap_or(String::from("")),
-                            };
+                        if commit.sha.starts_with(&prev_tag_hash) {
+                            break 'outer;
                         }
+
+                        changelog.commits.push(commit.into());
                     }
                 }
                 Err(err) => {
@@ -188,28 +739,948 @@ async fn generate_repo_report(
                 }
             }
         }
+
+        let reverts = cancel_reverts(&mut changelog.commits);
+        changelog.dropped = filters.apply(
+            &git,
+            &format!("{prev_tag_hash}..{tag_hash}"),
+            &mut changelog.commits,
+        )?;
+        changelog.dropped.insert("reverts".to_string(), reverts);
+
+        summarize_commits(&owner, &repo, &mut changelog, &bots, is_link_issues).await;
+
+        if is_go_mod_diff {
+            diff_go_mod(
+                &mut changelog,
+                git.show_file(&prev_tag_hash, "go.mod"),
+                git.show_file(&tag_hash, "go.mod"),
+            );
+        }
+    }
+
+    Ok(changelog)
+}
+
+/// Same as `generate_repo_report`, but resolves tags and lists commits by the GitHub API instead
+/// of cloning the repo.
+async fn generate_repo_report_by_api(
+    owner: String,
+    repo: String,
+    opts: RepoReportOptions,
+) -> anyhow::Result<RepoChangelog> {
+    let RepoReportOptions {
+        branch,
+        tag,
+        prev_tag,
+        is_public,
+        filters,
+        bots,
+        is_go_mod_diff,
+        is_link_issues,
+        ..
+    } = opts;
+
+    let head = if tag.is_empty() { &branch } else { &tag };
+    let mut prev_tag = prev_tag.unwrap_or_default();
+
+    if prev_tag.is_empty() {
+        let tags = list_tags(&owner, &repo).await?;
+
+        for candidate in previous_tag_candidates(&tags, &tag, is_public) {
+            if is_ancestor(&owner, &repo, &candidate, head).await? {
+                log::info!(
+                    "Found previous tag: {candidate}, owner: {owner} repo: {repo} branch: {branch}"
+                );
+                prev_tag = candidate;
+                break;
+            }
+        }
+    }
+
+    let mut changelog = RepoChangelog {
+        repo_ref: format!("{owner}/{repo}"),
+        branch: branch.clone(),
+        tag: tag.clone(),
+        prev_tag: prev_tag.clone(),
+        commits: vec![],
+        dependency_updates: vec![],
+        module_changes: vec![],
+        dropped: indexmap! {},
+    };
+
+    if prev_tag.is_empty() {
+        log::debug!("Failed to get previous tag of {}", changelog.repo_ref);
+        return Ok(changelog);
+    }
+
+    let mut commits = compare_commits(&owner, &repo, &prev_tag, head).await?;
+    commits.reverse();
+    changelog.commits = commits.iter().map(|it| it.into()).collect();
+
+    let reverts = cancel_reverts(&mut changelog.commits);
+    changelog.dropped = filters.apply_with(
+        |first_parent, pathspecs| {
+            if !pathspecs.is_empty() {
+                return Err(anyhow!("path filters require cloning the repo"));
+            }
+
+            Ok(if first_parent {
+                first_parent_shas(&commits)
+            } else {
+                commits.iter().map(|it| it.sha.clone()).collect()
+            })
+        },
+        &mut changelog.commits,
+    )?;
+    changelog.dropped.insert("reverts".to_string(), reverts);
+
+    summarize_commits(&owner, &repo, &mut changelog, &bots, is_link_issues).await;
+
+    if is_go_mod_diff {
+        diff_go_mod(
+            &mut changelog,
+            get_file(&owner, &repo, &prev_tag, "go.mod").await,
+            get_file(&owner, &repo, head, "go.mod").await,
+        );
+    }
+
+    Ok(changelog)
+}
+
+/// Returns the semver tags older than the tag from newest to oldest, or all of them if the tag
+/// is empty. Only returns public releases if `is_public`.
+fn previous_tag_candidates(tags: &[String], tag: &str, is_public: bool) -> Vec<String> {
+    let parse = |it: &str| semver::Version::parse(it.trim_start_matches('v')).ok();
+    let current = parse(tag);
+
+    let mut candidates: Vec<(semver::Version, &String)> = tags
+        .iter()
+        .filter_map(|it| parse(it).map(|version| (version, it)))
+        .filter(|(version, _)| current.as_ref().is_none_or(|it| version < it))
+        .filter(|(version, _)| !is_public || version.pre.is_empty())
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0));
+
+    candidates.into_iter().map(|(_, it)| it.clone()).collect()
+}
+
+/// Returns the commits reachable from the newest commit by following first parents only. The
+/// commits are ordered from newest to oldest.
+fn first_parent_shas(commits: &[Commit]) -> Vec<String> {
+    let parents: HashMap<&str, Option<&str>> = commits
+        .iter()
+        .map(|it| {
+            (
+                it.sha.as_str(),
+                it.parents.first().map(|parent| parent.sha.as_str()),
+            )
+        })
+        .collect();
+
+    let mut shas = vec![];
+    let mut next = commits.first().map(|it| it.sha.as_str());
+    while let Some(sha) = next.filter(|it| parents.contains_key(it)) {
+        shas.push(sha.to_string());
+        next = parents[sha];
+    }
+
+    shas
+}
+
+/// Moves the bot commits into dependency updates, and links the issues of the other commits.
+async fn summarize_commits(
+    owner: &str,
+    repo: &str,
+    changelog: &mut RepoChangelog,
+    bots: &[String],
+    is_link_issues: bool,
+) {
+    let (bot_commits, commits): (Vec<ChangelogCommit>, Vec<ChangelogCommit>) =
+        std::mem::take(&mut changelog.commits)
+            .into_iter()
+            .partition(|commit| bots.iter().any(|bot| commit.is_authored_by(bot)));
+
+    changelog.commits = commits;
+
+    if is_link_issues {
+        link_issues(owner, repo, &mut changelog.commits).await;
+    }
+
+    changelog.dependency_updates = bot_commits
+        .into_iter()
+        .map(DependencyUpdate::parse)
+        .collect();
+}
+
+fn diff_go_mod(
+    changelog: &mut RepoChangelog,
+    prev_content: anyhow::Result<String>,
+    content: anyhow::Result<String>,
+) {
+    match content {
+        Ok(content) => {
+            changelog.module_changes = diff_requires(&prev_content.unwrap_or_default(), &content);
+        }
+        Err(err) => {
+            log::debug!("Failed to read go.mod of {}: {:?}", changelog.repo_ref, err);
+        }
+    }
+}
+
+/// Drops the pairs of reverted commits and their reverts from the commits ordered from newest
+/// to oldest, and marks the reverts of commits before the range. Returns the number of dropped commits.
+fn cancel_reverts(commits: &mut Vec<ChangelogCommit>) -> usize {
+    lazy_static! {
+        static ref REVERT_REG: Regex = Regex::new(r"This reverts commit ([0-9a-f]{7,40})").unwrap();
+    }
+
+    let mut dropped = HashSet::new();
+
+    for i in 0..commits.len() {
+        if dropped.contains(&i) {
+            continue;
+        }
+
+        let Some(reverted_sha) = REVERT_REG
+            .captures(&commits[i].message)
+            .map(|caps| caps[1].to_string())
+        else {
+            continue;
+        };
+
+        match commits
+            .iter()
+            .enumerate()
+            .skip(i + 1)
+            .find(|(_, it)| it.sha.starts_with(&reverted_sha))
+        {
+            Some((j, _)) if !dropped.contains(&j) => {
+                dropped.insert(i);
+                dropped.insert(j);
+            }
+            _ => commits[i].reverted_sha = Some(reverted_sha),
+        }
+    }
+
+    let mut index = 0;
+    commits.retain(|_| {
+        index += 1;
+        !dropped.contains(&(index - 1))
+    });
+
+    dropped.len()
+}
+
+/// Resolves the issues referenced by the commit messages, skipping pull requests.
+async fn link_issues(owner: &str, repo: &str, commits: &mut [ChangelogCommit]) {
+    let mut resolved_issues: HashMap<IssueRef, Option<LinkedIssue>> = HashMap::new();
+
+    for commit in commits {
+        for reference in parse_issue_refs(&commit.message, owner, repo) {
+            if !resolved_issues.contains_key(&reference) {
+                let issue = resolve_issue(&reference).await.unwrap_or_else(|err| {
+                    log::debug!("Failed to get issue {}: {:?}", reference, err);
+                    Some(LinkedIssue::new(reference.clone()))
+                });
+                resolved_issues.insert(reference.clone(), issue);
+            }
+
+            if let Some(issue) = &resolved_issues[&reference] {
+                commit.issues.push(issue.clone());
+            }
+        }
+    }
+}
+
+fn compile_regexes(pats: &[String]) -> anyhow::Result<Vec<Regex>> {
+    pats.iter()
+        .map(|it| Regex::new(it).map_err(|err| anyhow!("invalid regex {it}: {err}")))
+        .collect()
+}
+
+/// Converts a heading into the anchor GitHub generates for it.
+fn heading_anchor(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Formats the count with the noun, e.g. "1 commit" and "2 commits".
+fn pluralize(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("{count} {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
+
+fn render_repo_stats(changelog: &RepoChangelog) -> String {
+    let contributors = changelog.contributors();
+    let mut stats = vec![pluralize(changelog.commits.len(), "commit")];
+    if !changelog.dependency_updates.is_empty() {
+        stats.push(pluralize(
+            changelog.dependency_updates.len(),
+            "dependency update",
+        ));
+    }
+    stats.extend([format!(
+        "{} ({})",
+        pluralize(contributors.len(), "contributor"),
+        contributors.iter().cloned().collect::<Vec<_>>().join(" ")
+    )]);
+
+    let tag = if changelog.tag.is_empty() {
+        &changelog.branch
+    } else {
+        &changelog.tag
+    };
+    stats.push(format!("{} → {}", changelog.prev_tag, tag));
+
+    if let Some(url) = changelog.compare_url() {
+        stats.push(format!("[compare]({url})"));
+    }
+
+    let mut str = format!("**Stats**: {}\n", stats.join(", "));
+
+    let dropped: Vec<String> = changelog
+        .dropped
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(name, count)| format!("{count} by {name}"))
+        .collect();
+    if !dropped.is_empty() {
+        str += &format!("\n**Filtered out**: {}\n", dropped.join(", "));
+    }
+
+    str
+}
+
+fn render_commits(changelog: &RepoChangelog) -> String {
+    let mut commits = String::new();
+
+    for commit in &changelog.commits {
+        let revert = commit
+            .reverted_sha
+            .as_ref()
+            .map(|it| format!(" (revert of an earlier change {})", &it[0..8.min(it.len())]))
+            .unwrap_or_default();
+
+        commits += &format!(
+            "- {} [{}]({}) by {}{}{}\n",
+            commit.subject(),
+            commit.short_sha(),
+            commit.html_url,
+            commit.author(),
+            revert,
+            render_linked_issues(&commit.issues),
+        );
+    }
+
+    commits
+}
+
+fn render_linked_issues(issues: &[LinkedIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| match &issue.state {
+            Some(state) => format!(" [{}]({}) ({})", issue.reference, issue.html_url, state),
+            None => format!(" [{}]({})", issue.reference, issue.html_url),
+        })
+        .collect()
+}
+
+type RepoCommit<'a> = (&'a str, &'a ChangelogCommit);
+
+fn render_issue_groups(changelogs: &[RepoChangelog]) -> String {
+    let mut groups: BTreeMap<IssueRef, (&LinkedIssue, Vec<RepoCommit>)> = BTreeMap::new();
+    let mut unreferenced = vec![];
+
+    for changelog in changelogs {
+        for commit in &changelog.commits {
+            if commit.issues.is_empty() {
+                unreferenced.push((changelog.repo_ref.as_str(), commit));
+            }
+
+            for issue in &commit.issues {
+                groups
+                    .entry(issue.reference.clone())
+                    .or_insert_with(|| (issue, vec![]))
+                    .1
+                    .push((changelog.repo_ref.as_str(), commit));
+            }
+        }
     }
 
-    changelog = if is_markdown_folding {
+    let render_commit = |repo_ref: &str, commit: &ChangelogCommit| {
+        format!(
+            "- {}: {} [{}]({})\n",
+            repo_ref,
+            commit.subject(),
+            commit.short_sha(),
+            commit.html_url
+        )
+    };
+
+    let mut str = String::from("\n## Changes by Issue\n");
+
+    for (issue, commits) in groups.values() {
+        str += &format!(
+            "\n### [{}]({}) {}{}\n",
+            issue.reference,
+            issue.html_url,
+            issue.title.as_deref().unwrap_or_default(),
+            issue
+                .state
+                .as_ref()
+                .map(|it| format!(" ({it})"))
+                .unwrap_or_default()
+        );
+        for (repo_ref, commit) in commits {
+            str += &render_commit(repo_ref, commit);
+        }
+    }
+
+    if !unreferenced.is_empty() {
+        str += "\n### No Issue Referenced\n";
+        for (repo_ref, commit) in unreferenced {
+            str += &render_commit(repo_ref, commit);
+        }
+    }
+
+    str
+}
+
+fn render_dependency_updates(changelog: &RepoChangelog) -> String {
+    if changelog.dependency_updates.is_empty() {
+        return String::new();
+    }
+
+    let mut str = format!(
+        "\n<details>\n<summary>Dependency updates ({})</summary>\n\n",
+        changelog.dependency_updates.len()
+    );
+
+    for update in &changelog.dependency_updates {
+        let versions = match (&update.from, &update.to) {
+            (Some(from), Some(to)) => format!(": {from} → {to}"),
+            (None, Some(to)) => format!(": → {to}"),
+            _ => String::new(),
+        };
+
+        str += &format!(
+            "- {}{} [{}]({})\n",
+            update.dependency,
+            versions,
+            update.commit.short_sha(),
+            update.commit.html_url,
+        );
+    }
+
+    str + "</details>\n"
+}
+
+fn render_module_changes(changelog: &RepoChangelog) -> String {
+    if changelog.module_changes.is_empty() {
+        return String::new();
+    }
+
+    let (owner, _) = changelog.repo_ref.split_once('/').unwrap_or_default();
+    let org_prefix = format!("github.com/{owner}/");
+
+    let mut str = String::from("\n**Dependency changes**\n\n");
+
+    for change in &changelog.module_changes {
+        let marker = if change.module.starts_with(&org_prefix) {
+            format!(" **[{owner}]**")
+        } else {
+            String::new()
+        };
+
+        str += &match (&change.from, &change.to) {
+            (Some(from), Some(to)) => {
+                format!("- `{}`{}: {} → {}\n", change.module, marker, from, to)
+            }
+            (None, Some(to)) => format!("- `{}`{}: added {}\n", change.module, marker, to),
+            (Some(from), None) => format!("- `{}`{}: removed {}\n", change.module, marker, from),
+            (None, None) => String::new(),
+        };
+    }
+
+    str
+}
+
+/// Renders the commits, dependency updates and go.mod changes of the repo, shared by the
+/// printed changelog and the changelog files.
+fn render_changes(changelog: &RepoChangelog) -> String {
+    render_commits(changelog)
+        + &render_dependency_updates(changelog)
+        + &render_module_changes(changelog)
+}
+
+fn render_repo_changelog(changelog: &RepoChangelog, is_markdown_folding: bool) -> String {
+    let commits = render_changes(changelog);
+
+    if is_markdown_folding {
         formatdoc! {"
+            <a name=\"{anchor}\"></a>
             <details>
             <summary>{repo}</summary>
 
-            {changelog}
+            {stats}
+            {commits}
             </details>
             ",
-            repo=git.repo.repo_ref(),
-            changelog=changelog,
+            anchor=heading_anchor(&changelog.repo_ref),
+            repo=changelog.repo_ref,
+            stats=render_repo_stats(changelog),
+            commits=commits,
         }
     } else {
         formatdoc! {"
             ### {repo}
-            {changelog}
+            {stats}
+            {commits}
             ",
-            repo=git.repo.repo_ref(),
-            changelog=changelog,
+            repo=changelog.repo_ref,
+            stats=render_repo_stats(changelog),
+            commits=commits,
         }
-    };
+    }
+}
 
-    Ok(changelog)
+pub(crate) fn render_changelog(
+    changelogs: &[RepoChangelog],
+    errors: &[RepoError],
+    is_markdown_folding: bool,
+) -> String {
+    let (changed, unchanged): (Vec<&RepoChangelog>, Vec<&RepoChangelog>) =
+        changelogs.iter().partition(|it| it.has_changes());
+
+    let mut str = String::new();
+
+    if !changed.is_empty() || !errors.is_empty() {
+        str += "## Table of Contents\n";
+        for changelog in &changed {
+            str += &format!(
+                "- [{}](#{}) ({})\n",
+                changelog.repo_ref,
+                heading_anchor(&changelog.repo_ref),
+                changelog.change_counts()
+            );
+        }
+        if !errors.is_empty() {
+            str += &format!(
+                "- [Errors](#errors) ({})\n",
+                pluralize(errors.len(), "repo")
+            );
+        }
+        str += "\n";
+    }
+
+    for changelog in &changed {
+        str += &render_repo_changelog(changelog, is_markdown_folding);
+    }
+
+    if !unchanged.is_empty() {
+        str += &format!(
+            "**No changes**: {}\n",
+            unchanged
+                .iter()
+                .map(|it| it.repo_ref.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    if !errors.is_empty() {
+        str += "\n### Errors\n";
+        for error in errors {
+            str += &format!("- {}: {}\n", error.repo_ref, error.reason);
+        }
+    }
+
+    str
+}
+
+/// Adds a section of the version into the content of a changelog file.
+///
+/// The "Unreleased" heading is turned into the version heading if it exists, otherwise the new
+/// section is put before the first versioned section. Returns `None` if the version already has
+/// a section.
+fn update_changelog_file(
+    content: &str,
+    version: &str,
+    date: &str,
+    entries: &str,
+) -> Option<String> {
+    let heading_reg = Regex::new(r"^(#{2,})\s+(.+?)\s*$").unwrap();
+    let unreleased_reg = Regex::new(r"(?i)^\[?unreleased\]?$").unwrap();
+    // Match the headings of the version with or without the v prefix
+    let version_reg = Regex::new(&format!(
+        r"^\[?v?{}\]?(\s|$)",
+        regex::escape(version.trim_start_matches('v'))
+    ))
+    .unwrap();
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut section_index = None;
+    let mut level = "##".to_string();
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(caps) = heading_reg.captures(line) else {
+            continue;
+        };
+
+        if version_reg.is_match(&caps[2]) {
+            return None;
+        }
+
+        if section_index.is_none() {
+            section_index = Some(index);
+            level = caps[1].to_string();
+        }
+    }
+
+    let heading = format!("{level} {version} ({date})");
+    let mut new_lines: Vec<String> = vec![];
+
+    match section_index {
+        Some(index) => {
+            let caps = heading_reg.captures(lines[index]).unwrap();
+            let is_unreleased = unreleased_reg.is_match(&caps[2]);
+
+            new_lines.extend(lines[..index].iter().map(|it| it.to_string()));
+            new_lines.push(heading);
+            new_lines.push(String::new());
+            new_lines.push(entries.trim_end().to_string());
+
+            if is_unreleased {
+                new_lines.extend(lines[index + 1..].iter().map(|it| it.to_string()));
+            } else {
+                new_lines.push(String::new());
+                new_lines.extend(lines[index..].iter().map(|it| it.to_string()));
+            }
+        }
+        None => {
+            new_lines.extend(lines.iter().map(|it| it.to_string()));
+            if lines.last().is_some_and(|it| !it.trim().is_empty()) {
+                new_lines.push(String::new());
+            }
+            new_lines.push(heading);
+            new_lines.push(String::new());
+            new_lines.push(entries.trim_end().to_string());
+        }
+    }
+
+    Some(new_lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use maplit::hashmap;
+
+    use super::*;
+
+    #[test]
+    fn test_heading_anchor() {
+        let data = hashmap! {
+            "longhorn/longhorn-manager" => "longhornlonghorn-manager",
+            "Table of Contents" => "table-of-contents",
+            "longhorn/go_common.libs" => "longhorngo_commonlibs",
+        };
+
+        for (heading, expected) in data {
+            assert_eq!(heading_anchor(heading), expected);
+        }
+    }
+
+    #[test]
+    fn test_render_changelog() {
+        let commit = ChangelogCommit {
+            sha: "0123456789abcdef".to_string(),
+            message: "fix: foo\n\nSigned-off-by: a".to_string(),
+            html_url: "https://github.com/longhorn/b/commit/0123456789abcdef".to_string(),
+            author_login: Some("dev".to_string()),
+            ..Default::default()
+        };
+        let changelogs = vec![
+            RepoChangelog {
+                repo_ref: "longhorn/a".to_string(),
+                branch: "master".to_string(),
+                tag: "v1.0.1".to_string(),
+                prev_tag: "v1.0.0".to_string(),
+                ..Default::default()
+            },
+            RepoChangelog {
+                repo_ref: "longhorn/b".to_string(),
+                branch: "master".to_string(),
+                tag: "v1.0.1".to_string(),
+                prev_tag: "v1.0.0".to_string(),
+                commits: vec![commit.clone()],
+                dependency_updates: vec![DependencyUpdate::parse(ChangelogCommit {
+                    message: "chore(deps): bump foo from 1.0.0 to 1.1.0".to_string(),
+                    ..commit
+                })],
+                module_changes: vec![ModuleChange {
+                    module: "github.com/longhorn/backupstore".to_string(),
+                    from: Some("v0.1.0".to_string()),
+                    to: Some("v0.2.0".to_string()),
+                }],
+                dropped: indexmap! { "subjects".to_string() => 2 },
+            },
+            RepoChangelog {
+                repo_ref: "longhorn/d".to_string(),
+                branch: "master".to_string(),
+                tag: "v1.0.1".to_string(),
+                prev_tag: "v1.0.0".to_string(),
+                module_changes: vec![ModuleChange {
+                    module: "k8s.io/api".to_string(),
+                    from: Some("v0.28.0".to_string()),
+                    to: Some("v0.28.1".to_string()),
+                }],
+                ..Default::default()
+            },
+        ];
+
+        let errors = vec![RepoError {
+            repo_ref: "longhorn/c".to_string(),
+            reason: "previous tag not found".to_string(),
+        }];
+
+        let str = render_changelog(&changelogs, &errors, false);
+
+        assert!(str.starts_with(
+            "## Table of Contents\n- [longhorn/b](#longhornb) (1 commit, 1 dependency update, 1 go.mod change)\n- [longhorn/d](#longhornd) (1 go.mod change)\n- [Errors](#errors) (1 repo)\n"
+        ));
+        assert!(str.contains(
+            "<summary>Dependency updates (1)</summary>\n\n- foo: 1.0.0 → 1.1.0 [01234567]"
+        ));
+        assert!(str.contains("**Stats**: 1 commit, 1 dependency update, 1 contributor (@dev), v1.0.0 → v1.0.1, [compare](https://github.com/longhorn/b/compare/v1.0.0...v1.0.1)"));
+        assert!(str.contains("- fix: foo [01234567](https://github.com/longhorn/b/commit/0123456789abcdef) by @dev\n"));
+        assert!(
+            str.contains("- `github.com/longhorn/backupstore` **[longhorn]**: v0.1.0 → v0.2.0\n")
+        );
+        assert!(str.contains("**Filtered out**: 2 by subjects\n"));
+        assert!(str.contains("**No changes**: longhorn/a\n"));
+        assert!(str.ends_with("### Errors\n- longhorn/c: previous tag not found\n"));
+    }
+
+    #[test]
+    fn test_commit_filters() -> anyhow::Result<()> {
+        let filters = CommitFilters {
+            exclude_subjects: compile_regexes(&[
+                r"^chore\(deps\)".to_string(),
+                "^Merge ".to_string(),
+            ])?,
+            exclude_authors: vec!["@renovate[bot]".to_string()],
+            ..Default::default()
+        };
+
+        let data = vec![
+            ("fix: foo", "dev", true),
+            ("chore(deps): update go", "dev", false),
+            ("Merge pull request #1 from dev/foo", "dev", false),
+            ("fix: bar", "renovate[bot]", false),
+        ];
+
+        for (subject, login, expected) in data {
+            let commit = ChangelogCommit {
+                message: subject.to_string(),
+                author_login: Some(login.to_string()),
+                ..Default::default()
+            };
+
+            assert_eq!(
+                filters.is_subject_kept(commit.subject()) && filters.is_author_kept(&commit),
+                expected
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_dependency_update() {
+        let data =
+            vec![
+            (
+                "chore(deps): bump golang.org/x/net from 0.17.0 to 0.23.0",
+                ("golang.org/x/net", Some("0.17.0"), Some("0.23.0")),
+            ),
+            (
+                "fix(deps): update module github.com/longhorn/go-common-libs to v0.0.0-20240308",
+                ("github.com/longhorn/go-common-libs", None, Some("v0.0.0-20240308")),
+            ),
+            (
+                "chore(deps): update golang docker tag to v1.22",
+                ("golang", None, Some("v1.22")),
+            ),
+            (
+                "fix(deps): update minor dependencies",
+                ("update minor dependencies", None, None),
+            ),
+        ];
+
+        for (subject, (dependency, from, to)) in data {
+            let update = DependencyUpdate::parse(ChangelogCommit {
+                message: subject.to_string(),
+                ..Default::default()
+            });
+
+            assert_eq!(update.dependency, dependency);
+            assert_eq!(update.from.as_deref(), from);
+            assert_eq!(update.to.as_deref(), to);
+        }
+    }
+
+    #[test]
+    fn test_render_issue_groups() {
+        let issue = LinkedIssue {
+            title: Some("Support foo".to_string()),
+            state: Some("closed".to_string()),
+            ..LinkedIssue::new(IssueRef {
+                owner: "longhorn".to_string(),
+                repo: "longhorn".to_string(),
+                number: 1234,
+            })
+        };
+        let changelogs = vec![RepoChangelog {
+            repo_ref: "longhorn/longhorn-manager".to_string(),
+            commits: vec![
+                ChangelogCommit {
+                    sha: "0123456789abcdef".to_string(),
+                    message: "feat: foo (longhorn/longhorn#1234)".to_string(),
+                    html_url: "url1".to_string(),
+                    issues: vec![issue],
+                    ..Default::default()
+                },
+                ChangelogCommit {
+                    sha: "fedcba9876543210".to_string(),
+                    message: "chore: bar".to_string(),
+                    html_url: "url2".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }];
+
+        assert_eq!(
+            render_issue_groups(&changelogs),
+            indoc! {"
+
+                ## Changes by Issue
+
+                ### [longhorn/longhorn#1234](https://github.com/longhorn/longhorn/issues/1234) Support foo (closed)
+                - longhorn/longhorn-manager: feat: foo (longhorn/longhorn#1234) [01234567](url1)
+
+                ### No Issue Referenced
+                - longhorn/longhorn-manager: chore: bar [fedcba98](url2)
+            "}
+        );
+    }
+
+    #[test]
+    fn test_previous_tag_candidates() {
+        let tags: Vec<String> = [
+            "v1.5.0",
+            "v1.6.0-rc1",
+            "v1.6.0",
+            "v1.5.1",
+            "latest",
+            "v1.7.0",
+        ]
+        .iter()
+        .map(|it| it.to_string())
+        .collect();
+
+        let data = vec![
+            ("v1.6.0", false, vec!["v1.6.0-rc1", "v1.5.1", "v1.5.0"]),
+            ("v1.6.1", true, vec!["v1.6.0", "v1.5.1", "v1.5.0"]),
+            (
+                "",
+                false,
+                vec!["v1.7.0", "v1.6.0", "v1.6.0-rc1", "v1.5.1", "v1.5.0"],
+            ),
+        ];
+
+        for (tag, is_public, expected) in data {
+            assert_eq!(previous_tag_candidates(&tags, tag, is_public), expected);
+        }
+    }
+
+    #[test]
+    fn test_cancel_reverts() {
+        let commit = |sha: &str, message: &str| ChangelogCommit::for_test(sha, message, None);
+
+        // ordered from newest to oldest
+        let mut commits = vec![
+            commit(
+                "ccccccc1",
+                "Revert \"Revert \"feat: b\"\"\n\nThis reverts commit bbbbbbb1.",
+            ),
+            commit(
+                "bbbbbbb1",
+                "Revert \"feat: b\"\n\nThis reverts commit aaaaaaa2.",
+            ),
+            commit(
+                "aaaaaaa3",
+                "Revert \"feat: x\"\n\nThis reverts commit 99999999.",
+            ),
+            commit("aaaaaaa2", "feat: b"),
+            commit("aaaaaaa1", "feat: a"),
+        ];
+
+        assert_eq!(cancel_reverts(&mut commits), 2);
+        assert_eq!(
+            commits.iter().map(|it| it.sha.as_str()).collect::<Vec<_>>(),
+            vec!["aaaaaaa3", "aaaaaaa2", "aaaaaaa1"]
+        );
+        assert_eq!(commits[0].reverted_sha.as_deref(), Some("99999999"));
+    }
+
+    #[test]
+    fn test_update_changelog_file() {
+        let data = vec![
+            hashmap! {
+                "content" => "# Changelog\n",
+                "expected" => "# Changelog\n\n## v1.0.1 (2024-01-01)\n\n- fix: foo\n",
+            },
+            hashmap! {
+                "content" => "# Changelog\n\n## v1.0.0 (2023-12-01)\n\n- feat: bar\n",
+                "expected" => "# Changelog\n\n## v1.0.1 (2024-01-01)\n\n- fix: foo\n\n## v1.0.0 (2023-12-01)\n\n- feat: bar\n",
+            },
+            hashmap! {
+                "content" => "# Changelog\n\n## [Unreleased]\n\n- note\n\n## v1.0.0\n",
+                "expected" => "# Changelog\n\n## v1.0.1 (2024-01-01)\n\n- fix: foo\n\n- note\n\n## v1.0.0\n",
+            },
+        ];
+
+        for d in data {
+            let result =
+                update_changelog_file(d["content"], "v1.0.1", "2024-01-01", "- fix: foo\n");
+
+            assert_eq!(result.as_deref(), Some(d["expected"]));
+        }
+
+        for content in ["## v1.0.1\n", "## 1.0.1 (2024-01-01)\n", "## [1.0.1]\n"] {
+            assert!(
+                update_changelog_file(content, "v1.0.1", "2024-01-01", "- fix: foo\n").is_none(),
+                "{content}"
+            );
+        }
+        assert!(
+            update_changelog_file("## 1.0.1\n", "1.0.1", "2024-01-01", "- fix: foo\n").is_none()
+        );
+        assert!(
+            update_changelog_file("## v1.0.10\n", "v1.0.1", "2024-01-01", "- fix: foo\n").is_some()
+        );
+    }
 }
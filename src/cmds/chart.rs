@@ -0,0 +1,600 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use clap::{Args, Subcommand};
+use tracing_log::log;
+
+use crate::cmds::pr::{pull_request_options, MergeArgs};
+use crate::cmds::CliCommand;
+use crate::config::{Config, DeployManifest};
+use crate::git::{diff_untracked_file, GitCli, GitOperationTrait};
+use crate::github::{GithubCli, GithubOperationTrait};
+use crate::yamlpath::{get_yaml_path, set_yaml_path};
+use crate::{cmd, Cli};
+
+#[derive(Args)]
+#[command(about = "Manage the Helm chart of a release")]
+pub struct ChartArgs {
+    #[command(subcommand)]
+    command: ChartCommands,
+}
+
+#[derive(Subcommand)]
+enum ChartCommands {
+    Version(ChartVersionArgs),
+    Lint(ChartLintArgs),
+    Package(ChartPackageArgs),
+    Publish(ChartPublishArgs),
+    Render(ChartRenderArgs),
+}
+
+#[async_trait]
+impl CliCommand for ChartArgs {
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        match &self.command {
+            ChartCommands::Version(args) => args.run(cli).await,
+            ChartCommands::Lint(args) => args.run(cli).await,
+            ChartCommands::Package(args) => args.run(cli).await,
+            ChartCommands::Publish(args) => args.run(cli).await,
+            ChartCommands::Render(args) => args.run(cli).await,
+        }
+    }
+}
+
+#[derive(Args)]
+struct ChartSourceArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repo")]
+    repo: String,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, default_value = "chart", help = "Chart directory in the repo")]
+    chart: String,
+}
+
+impl ChartSourceArgs {
+    /// Clones the repo and returns the chart directory.
+    fn clone_chart(&self) -> anyhow::Result<PathBuf> {
+        let git = GitCli::new(self.owner.clone(), self.repo.clone());
+        git.clone_repo(&self.branch)?;
+
+        let chart_dir_path = git.repo.repo_dir_path().join(&self.chart);
+        if !chart_dir_path.join("Chart.yaml").exists() {
+            return Err(anyhow!("no Chart.yaml found in {:?}", chart_dir_path));
+        }
+
+        Ok(chart_dir_path)
+    }
+}
+
+#[derive(Args)]
+#[command(about = "Set the version and appVersion of the chart, and create a PR")]
+struct ChartVersionArgs {
+    #[command(flatten)]
+    source: ChartSourceArgs,
+
+    #[arg(long, help = "Tag")]
+    tag: String,
+
+    #[arg(long, help = "Commit message")]
+    message: Option<String>,
+
+    #[arg(long, help = "Dry run")]
+    dryrun: bool,
+
+    #[command(flatten)]
+    merge: MergeArgs,
+}
+
+#[async_trait]
+impl CliCommand for ChartVersionArgs {
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        let config = Config::load(cli.config.as_deref())?;
+        let chart_dir_path = self.source.clone_chart()?;
+        set_chart_version(&chart_dir_path, &self.tag)?;
+        helm_lint(&chart_dir_path)?;
+
+        if self.dryrun {
+            return Ok(());
+        }
+
+        let gh_client = GithubCli::new(self.source.owner.clone(), self.source.repo.clone());
+        let id = gh_client.create_pr(
+            &self.message.clone().unwrap_or_default(),
+            &self.tag,
+            &self.source.branch,
+            &format!("chart-version-{}", self.tag),
+            &pull_request_options(
+                &config,
+                &self.source.owner,
+                &self.source.repo,
+                &self.tag,
+                None,
+            ),
+        )?;
+        self.merge.merge_when_ready(&gh_client, id.trim()).await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+#[command(about = "Lint the chart")]
+struct ChartLintArgs {
+    #[command(flatten)]
+    source: ChartSourceArgs,
+}
+
+#[async_trait]
+impl CliCommand for ChartLintArgs {
+    async fn run(&self, _: &Cli) -> anyhow::Result<()> {
+        helm_lint(&self.source.clone_chart()?)
+    }
+}
+
+#[derive(Args)]
+#[command(about = "Package the chart of the branch as the version of a tag into a .tgz file")]
+struct ChartPackageArgs {
+    #[command(flatten)]
+    source: ChartSourceArgs,
+
+    #[arg(long, help = "Tag")]
+    tag: String,
+
+    #[arg(long, help = "Output directory of the package")]
+    output: PathBuf,
+}
+
+#[async_trait]
+impl CliCommand for ChartPackageArgs {
+    async fn run(&self, _: &Cli) -> anyhow::Result<()> {
+        let chart_dir_path = self.source.clone_chart()?;
+        set_chart_version(&chart_dir_path, &self.tag)?;
+        helm_lint(&chart_dir_path)?;
+
+        fs::create_dir_all(&self.output)?;
+        let package = helm_package(&chart_dir_path, &self.output.canonicalize()?)?;
+        println!("{}", package.display());
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+#[command(
+    about = "Package the chart of the branch as the version of a tag into the chart repo, update its index.yaml and create a PR"
+)]
+struct ChartPublishArgs {
+    #[command(flatten)]
+    source: ChartSourceArgs,
+
+    #[arg(long, help = "Tag")]
+    tag: String,
+
+    #[arg(long, help = "Chart repository repo")]
+    chart_repo: String,
+
+    #[arg(
+        long,
+        default_value = "gh-pages",
+        help = "Branch of the chart repository repo"
+    )]
+    chart_repo_branch: String,
+
+    #[arg(
+        long,
+        default_value = ".",
+        help = "Directory of index.yaml and the packages in the chart repository repo"
+    )]
+    index_dir: String,
+
+    #[arg(long, help = "Base URL of the packages in index.yaml")]
+    url: String,
+
+    #[arg(long, help = "Commit message")]
+    message: Option<String>,
+
+    #[arg(long, help = "Dry run")]
+    dryrun: bool,
+
+    #[command(flatten)]
+    merge: MergeArgs,
+}
+
+#[async_trait]
+impl CliCommand for ChartPublishArgs {
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        let config = Config::load(cli.config.as_deref())?;
+        let chart_dir_path = self.source.clone_chart()?;
+        set_chart_version(&chart_dir_path, &self.tag)?;
+        helm_lint(&chart_dir_path)?;
+
+        let git = GitCli::new(self.source.owner.clone(), self.chart_repo.clone());
+        git.clone_repo(&self.chart_repo_branch)?;
+        let index_dir_path = git.repo.repo_dir_path().join(&self.index_dir);
+        fs::create_dir_all(&index_dir_path)?;
+
+        let package_dir = tempfile::tempdir()?;
+        let package = helm_package(&chart_dir_path, package_dir.path())?;
+        log::info!("Packaged chart {:?}", package);
+
+        helm_repo_index(package_dir.path(), &index_dir_path, &self.url)?;
+
+        if self.dryrun {
+            return Ok(());
+        }
+
+        let gh_client = GithubCli::new(self.source.owner.clone(), self.chart_repo.clone());
+        let id = gh_client.create_pr(
+            &self.message.clone().unwrap_or_default(),
+            &self.tag,
+            &self.chart_repo_branch,
+            &format!("chart-publish-{}", self.tag),
+            &pull_request_options(
+                &config,
+                &self.source.owner,
+                &self.chart_repo,
+                &self.tag,
+                None,
+            ),
+        )?;
+        self.merge.merge_when_ready(&gh_client, id.trim()).await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+#[command(about = "Render the deploy manifest from the chart and show the diff")]
+struct ChartRenderArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repo")]
+    repo: String,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+}
+
+#[async_trait]
+impl CliCommand for ChartRenderArgs {
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        let config = Config::load(cli.config.as_deref())?;
+        let git = GitCli::new(self.owner.clone(), self.repo.clone());
+        git.clone_repo(&self.branch)?;
+
+        let diff = write_deploy_manifest(git.repo.repo_dir_path(), &config.deploy_manifest)?;
+        match diff.is_empty() {
+            true => println!("No changes in {}", config.deploy_manifest.output),
+            false => println!("{}", diff),
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders the deploy manifest from the chart of the repo by `helm template`.
+fn render_deploy_manifest(
+    repo_dir_path: &Path,
+    manifest: &DeployManifest,
+) -> anyhow::Result<String> {
+    log::info!(
+        "Rendering {} from {} in {:?}",
+        manifest.output,
+        manifest.chart,
+        repo_dir_path
+    );
+
+    let mut args = vec![
+        "template",
+        &manifest.release_name,
+        &manifest.chart,
+        "--namespace",
+        &manifest.namespace,
+        "--no-hooks",
+    ];
+    for values in &manifest.values {
+        args.extend(["--values", values]);
+    }
+    let output = String::from_utf8(cmd!("helm", repo_dir_path, &args).stdout)?;
+
+    Ok(normalize_deploy_manifest(&output, manifest))
+}
+
+/// Trims the trailing whitespaces and blank lines of the rendered manifest, and prepends the
+/// Namespace object if required.
+fn normalize_deploy_manifest(rendered: &str, manifest: &DeployManifest) -> String {
+    let mut lines: Vec<&str> = rendered.lines().map(|it| it.trim_end()).collect();
+    while lines.last().is_some_and(|it| it.is_empty()) {
+        lines.pop();
+    }
+    let start = lines.iter().position(|it| !it.is_empty()).unwrap_or(0);
+
+    let mut content = String::new();
+    if manifest.create_namespace {
+        content += &format!(
+            "---\napiVersion: v1\nkind: Namespace\nmetadata:\n  name: {}\n",
+            manifest.namespace
+        );
+    }
+    for line in &lines[start..] {
+        content += line;
+        content += "\n";
+    }
+
+    content
+}
+
+/// Writes the rendered deploy manifest into the repo, and returns the diff against the
+/// committed file.
+pub(crate) fn write_deploy_manifest(
+    repo_dir_path: &Path,
+    manifest: &DeployManifest,
+) -> anyhow::Result<String> {
+    let content = render_deploy_manifest(repo_dir_path, manifest)?;
+
+    let output_path = repo_dir_path.join(&manifest.output);
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&output_path, content)?;
+
+    let tracked = cmd!(
+        "git",
+        repo_dir_path,
+        ["ls-files", "--", manifest.output.as_str()]
+    );
+    if tracked.stdout.is_empty() {
+        return diff_untracked_file(repo_dir_path, &manifest.output);
+    }
+
+    let diff = cmd!(
+        "git",
+        repo_dir_path,
+        ["diff", "--no-color", "--", manifest.output.as_str()]
+    );
+
+    Ok(String::from_utf8(diff.stdout)?)
+}
+
+/// Sets the version of Chart.yaml to the tag without the v prefix, and the appVersion to the
+/// tag, keeping the v prefix only if the current appVersion has one.
+fn update_chart_yaml(content: &str, tag: &str) -> anyhow::Result<String> {
+    let version = tag.trim_start_matches('v');
+    semver::Version::parse(version)
+        .map_err(|err| anyhow!("chart version requires a semver tag, {}: {}", tag, err))?;
+
+    let app_version = match get_yaml_path(content, "appVersion")?.starts_with('v') {
+        true => format!("v{}", version),
+        false => version.to_string(),
+    };
+
+    let content = set_yaml_path(content, "version", version)?;
+    set_yaml_path(&content, "appVersion", &app_version)
+}
+
+fn set_chart_version(chart_dir_path: &Path, tag: &str) -> anyhow::Result<()> {
+    log::info!("Setting chart version of {:?} to {}", chart_dir_path, tag);
+
+    let chart_yaml_path = chart_dir_path.join("Chart.yaml");
+    let content = fs::read_to_string(&chart_yaml_path)?;
+    fs::write(&chart_yaml_path, update_chart_yaml(&content, tag)?)?;
+
+    Ok(())
+}
+
+fn helm_lint(chart_dir_path: &Path) -> anyhow::Result<()> {
+    log::info!("Linting chart {:?}", chart_dir_path);
+    cmd!("helm", chart_dir_path, ["lint", "."]);
+
+    Ok(())
+}
+
+/// Packages the chart into the destination directory, and returns the path of the package.
+fn helm_package(chart_dir_path: &Path, destination: &Path) -> anyhow::Result<PathBuf> {
+    log::info!(
+        "Packaging chart {:?} into {:?}",
+        chart_dir_path,
+        destination
+    );
+
+    let output = String::from_utf8(
+        cmd!(
+            "helm",
+            chart_dir_path,
+            [
+                "package",
+                ".",
+                "--destination",
+                &destination.to_string_lossy()
+            ]
+        )
+        .stdout,
+    )?;
+
+    output
+        .lines()
+        .find_map(|line| line.split_once("saved it to: "))
+        .map(|(_, path)| PathBuf::from(path.trim()))
+        .ok_or_else(|| anyhow!("no package found in helm output: {}", output))
+}
+
+/// Indexes the new packages of the package directory into index.yaml of the index directory,
+/// and copies them there.
+///
+/// Only the new packages are indexed, because `helm repo index` regenerates the entries of all
+/// the packages in the directory, which would rewrite the created timestamps and URLs of the
+/// released versions.
+fn helm_repo_index(
+    package_dir_path: &Path,
+    index_dir_path: &Path,
+    url: &str,
+) -> anyhow::Result<()> {
+    log::info!(
+        "Updating index.yaml of {:?} with the packages of {:?}",
+        index_dir_path,
+        package_dir_path
+    );
+
+    let index_path = index_dir_path.canonicalize()?.join("index.yaml");
+    let index_path = index_path.to_string_lossy();
+    let mut args = vec!["repo", "index", ".", "--url", url];
+    if index_dir_path.join("index.yaml").exists() {
+        args.extend(["--merge", &index_path]);
+    }
+    cmd!("helm", package_dir_path, &args);
+
+    for entry in fs::read_dir(package_dir_path)? {
+        let path = entry?.path();
+        if path.is_file() {
+            fs::copy(&path, index_dir_path.join(path.file_name().unwrap()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_update_chart_yaml() -> anyhow::Result<()> {
+        let chart = indoc! {"
+            apiVersion: v1
+            name: longhorn
+            version: 1.6.0-dev
+            appVersion: v1.6.0-dev # the Longhorn version
+            kubeVersion: \">=1.21.0-0\"
+        "};
+
+        let updated = update_chart_yaml(chart, "v1.6.1-rc1")?;
+        assert_eq!(get_yaml_path(&updated, "version")?, "1.6.1-rc1");
+        assert_eq!(get_yaml_path(&updated, "appVersion")?, "v1.6.1-rc1");
+        assert!(updated.contains("appVersion: v1.6.1-rc1 # the Longhorn version"));
+
+        let updated = update_chart_yaml(&chart.replace("appVersion: v", "appVersion: "), "v1.6.1")?;
+        assert_eq!(get_yaml_path(&updated, "appVersion")?, "1.6.1");
+
+        assert!(update_chart_yaml(chart, "master-head").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires helm"]
+    fn test_helm_repo_index() -> anyhow::Result<()> {
+        let fixture = include_str!("testdata/chart-index.yaml");
+
+        let dir = tempfile::tempdir()?;
+        let chart_dir_path = dir.path().join("chart");
+        fs::create_dir_all(&chart_dir_path)?;
+        fs::write(
+            chart_dir_path.join("Chart.yaml"),
+            "apiVersion: v1\nname: longhorn\nversion: 1.6.1\nappVersion: v1.6.1\n",
+        )?;
+
+        // The released packages are in the index directory, and must not be reindexed
+        let index_dir_path = dir.path().join("index");
+        fs::create_dir_all(&index_dir_path)?;
+        fs::write(index_dir_path.join("index.yaml"), fixture)?;
+        fs::write(index_dir_path.join("longhorn-1.6.0.tgz"), "")?;
+        fs::write(index_dir_path.join("longhorn-1.5.3.tgz"), "")?;
+
+        let package_dir = tempfile::tempdir()?;
+        let package = helm_package(&chart_dir_path, package_dir.path())?;
+        let digest = String::from_utf8(
+            cmd!(
+                "sha256sum",
+                package_dir.path(),
+                [package.to_string_lossy().as_ref()]
+            )
+            .stdout,
+        )?;
+        helm_repo_index(
+            package_dir.path(),
+            &index_dir_path,
+            "https://charts.longhorn.io",
+        )?;
+
+        assert!(index_dir_path.join("longhorn-1.6.1.tgz").exists());
+
+        let entries = |content: &str| -> anyhow::Result<Vec<serde_yaml::Value>> {
+            let index: serde_yaml::Value = serde_yaml::from_str(content)?;
+            index["entries"]["longhorn"]
+                .as_sequence()
+                .cloned()
+                .ok_or_else(|| anyhow!("no longhorn entries"))
+        };
+        let released = entries(fixture)?;
+        let indexed = entries(&fs::read_to_string(index_dir_path.join("index.yaml"))?)?;
+        let find = |entries: &[serde_yaml::Value], version: &str| {
+            entries
+                .iter()
+                .find(|it| it["version"].as_str() == Some(version))
+                .cloned()
+        };
+
+        assert_eq!(indexed.len(), 3);
+        for entry in &released {
+            let version = entry["version"].as_str().unwrap_or_default();
+            assert_eq!(find(&indexed, version).as_ref(), Some(entry), "{version}");
+        }
+
+        let new = find(&indexed, "1.6.1").ok_or_else(|| anyhow!("no 1.6.1 entry"))?;
+        assert_eq!(
+            new["urls"][0],
+            "https://charts.longhorn.io/longhorn-1.6.1.tgz"
+        );
+        assert_eq!(
+            Some(new["digest"].as_str().unwrap_or_default()),
+            digest.split_whitespace().next()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_deploy_manifest() {
+        let rendered = indoc! {"
+
+            ---
+            # Source: longhorn/templates/serviceaccount.yaml
+            apiVersion: v1   
+            kind: ServiceAccount
+
+
+        "};
+
+        let manifest = DeployManifest::default();
+        assert_eq!(
+            normalize_deploy_manifest(rendered, &manifest),
+            indoc! {"
+                ---
+                apiVersion: v1
+                kind: Namespace
+                metadata:
+                  name: longhorn-system
+                ---
+                # Source: longhorn/templates/serviceaccount.yaml
+                apiVersion: v1
+                kind: ServiceAccount
+            "}
+        );
+
+        let manifest = DeployManifest {
+            create_namespace: false,
+            ..Default::default()
+        };
+        assert!(normalize_deploy_manifest(rendered, &manifest).starts_with("---\n# Source"));
+    }
+}
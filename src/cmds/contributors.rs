@@ -0,0 +1,488 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use async_trait::async_trait;
+use clap::Args;
+use indexmap::{IndexMap, IndexSet};
+use lazy_static::lazy_static;
+use octocrab::commits::PullRequestTarget;
+use regex::Regex;
+use tracing_log::log;
+
+use crate::cmds::changelog::{generate_repo_report, ChangelogCommit, RepoReportOptions};
+use crate::cmds::release::search_issues;
+use crate::cmds::{run_repo_tasks, CliCommand};
+use crate::git::{GitCli, GitOperationTrait};
+use crate::github::github_client;
+use crate::Cli;
+
+lazy_static! {
+    static ref CO_AUTHORED_BY_REG: Regex =
+        Regex::new(r"(?mi)^Co-authored-by:\s*(.+?)\s*<([^>]+)>\s*$").unwrap();
+    static ref NOREPLY_EMAIL_REG: Regex =
+        Regex::new(r"(?i)^(?:\d+\+)?([\w-]+)@users\.noreply\.github\.com$").unwrap();
+}
+
+#[derive(Args)]
+#[command(about = "Collect contributor statistics of repos between tags")]
+pub struct ContributorsArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repos")]
+    repos: Vec<String>,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, help = "Tag")]
+    tag: Option<String>,
+
+    #[arg(long, help = "Previous tag")]
+    prev_tag: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "14",
+        help = "Search commits since days if the previous tag has no date"
+    )]
+    since_days: i64,
+
+    #[arg(
+        long,
+        help = "Create logs from the last public release, not pre release"
+    )]
+    public: bool,
+
+    #[arg(
+        long,
+        default_value = "longhorn",
+        help = "GitHub repo of the milestone"
+    )]
+    repo: String,
+
+    #[arg(long, help = "Milestone to collect issue assignees")]
+    milestone: Option<String>,
+
+    #[arg(long, help = "Labels to exclude issues")]
+    exclude_labels: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        default_values = ["renovate[bot]", "dependabot[bot]"],
+        help = "Bot authors to exclude"
+    )]
+    bots: Vec<String>,
+
+    #[arg(long, help = "Maintainers to exclude")]
+    maintainers: Vec<String>,
+}
+
+/// A pull request merging commits of the range, with the logins of its reviewers.
+#[derive(Clone, Debug, Default)]
+struct PullRequestInfo {
+    author: Option<String>,
+    reviewers: Vec<String>,
+}
+
+/// The raw contributions to a repo in the range.
+#[derive(Clone, Debug, Default)]
+struct RepoContributions {
+    commits: Vec<ChangelogCommit>,
+    pull_requests: Vec<PullRequestInfo>,
+    /// Lowercase author emails before the range, `None` if there is no previous tag.
+    prior_emails: Option<HashSet<String>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Contributor {
+    /// The GitHub login prefixed with @, or the git author name if unknown.
+    name: String,
+    commits: usize,
+    co_authored_commits: usize,
+    pull_requests: usize,
+    reviews: usize,
+    issues: usize,
+    emails: BTreeSet<String>,
+    is_first_time: bool,
+}
+
+fn login_from_email(email: &str) -> Option<String> {
+    NOREPLY_EMAIL_REG
+        .captures(email)
+        .map(|caps| caps[1].to_string())
+}
+
+/// Returns the names and emails of the `Co-authored-by` trailers.
+fn parse_co_authors(message: &str) -> Vec<(String, String)> {
+    CO_AUTHORED_BY_REG
+        .captures_iter(message)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+/// Returns the contributor of the login, or the name if the login is unknown. Returns `None` if excluded.
+fn contributor_entry<'a>(
+    contributors: &'a mut IndexMap<String, Contributor>,
+    excluded: &[String],
+    login: Option<String>,
+    name: &str,
+) -> Option<&'a mut Contributor> {
+    let is_excluded = |it: &str| {
+        it.ends_with("[bot]")
+            || excluded
+                .iter()
+                .any(|e| e.trim_start_matches('@').eq_ignore_ascii_case(it))
+    };
+    if login.as_deref().is_some_and(is_excluded) || is_excluded(name) {
+        return None;
+    }
+
+    let display = login.map_or(name.to_string(), |it| format!("@{it}"));
+    Some(
+        contributors
+            .entry(display.to_lowercase())
+            .or_insert_with(|| Contributor {
+                name: display,
+                ..Default::default()
+            }),
+    )
+}
+
+/// Aggregates the contributions of all repos and the assignees of issues, skipping excluded names.
+fn collect_contributors(
+    repos: &[RepoContributions],
+    issue_assignees: &[String],
+    excluded: &[String],
+) -> Vec<Contributor> {
+    let mut contributors: IndexMap<String, Contributor> = IndexMap::new();
+
+    let mut email_logins = HashMap::new();
+    for commit in repos.iter().flat_map(|it| &it.commits) {
+        if let Some(login) = &commit.author_login {
+            email_logins.insert(commit.author_email.to_lowercase(), login.clone());
+        }
+    }
+
+    for repo in repos {
+        for commit in &repo.commits {
+            let login = commit
+                .author_login
+                .clone()
+                .or_else(|| login_from_email(&commit.author_email));
+            if let Some(it) =
+                contributor_entry(&mut contributors, excluded, login, &commit.author_name)
+            {
+                it.commits += 1;
+                it.emails.insert(commit.author_email.to_lowercase());
+            }
+
+            for (name, email) in parse_co_authors(&commit.message) {
+                let email = email.to_lowercase();
+                let login = email_logins
+                    .get(&email)
+                    .cloned()
+                    .or_else(|| login_from_email(&email));
+                if let Some(it) = contributor_entry(&mut contributors, excluded, login, &name) {
+                    it.co_authored_commits += 1;
+                    it.emails.insert(email);
+                }
+            }
+        }
+
+        for pr in &repo.pull_requests {
+            if let Some(author) = &pr.author {
+                if let Some(it) =
+                    contributor_entry(&mut contributors, excluded, Some(author.clone()), author)
+                {
+                    it.pull_requests += 1;
+                }
+            }
+
+            for reviewer in &pr.reviewers {
+                if pr.author.as_ref() == Some(reviewer) {
+                    continue;
+                }
+                if let Some(it) = contributor_entry(
+                    &mut contributors,
+                    excluded,
+                    Some(reviewer.clone()),
+                    reviewer,
+                ) {
+                    it.reviews += 1;
+                }
+            }
+        }
+    }
+
+    for assignee in issue_assignees {
+        if let Some(it) = contributor_entry(
+            &mut contributors,
+            excluded,
+            Some(assignee.clone()),
+            assignee,
+        ) {
+            it.issues += 1;
+        }
+    }
+
+    let prior_emails: Vec<&HashSet<String>> = repos
+        .iter()
+        .filter_map(|it| it.prior_emails.as_ref())
+        .collect();
+    let mut contributors: Vec<Contributor> = contributors.into_values().collect();
+    for contributor in &mut contributors {
+        contributor.is_first_time = !prior_emails.is_empty()
+            && !contributor.emails.is_empty()
+            && !contributor
+                .emails
+                .iter()
+                .any(|email| prior_emails.iter().any(|it| it.contains(email)));
+    }
+    contributors.sort_by_key(|it| it.name.trim_start_matches('@').to_lowercase());
+
+    contributors
+}
+
+#[async_trait]
+impl CliCommand for ContributorsArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let opts = RepoReportOptions::new(
+            self.branch.clone(),
+            self.tag.clone(),
+            self.prev_tag.clone(),
+            self.since_days,
+            self.public,
+            self.bots.clone(),
+        );
+
+        let repos = run_repo_tasks(&self.owner, &self.repos, |owner, repo| {
+            collect_repo_contributions(owner, repo, opts.clone())
+        })
+        .await?
+        .into_iter()
+        .collect::<anyhow::Result<Vec<RepoContributions>>>()?;
+
+        let mut issue_assignees = vec![];
+        if let Some(milestone) = &self.milestone {
+            let (_, issues) = search_issues(
+                &self.owner,
+                &self.repo,
+                milestone,
+                &[],
+                &self.exclude_labels.clone().unwrap_or_default(),
+                None,
+            )
+            .await?;
+
+            for issue in issues.iter().filter(|it| it.pull_request.is_none()) {
+                issue_assignees.extend(issue.assignees.iter().map(|it| it.login.clone()));
+            }
+        }
+
+        let excluded: Vec<String> = self.bots.iter().chain(&self.maintainers).cloned().collect();
+        let contributors = collect_contributors(&repos, &issue_assignees, &excluded);
+
+        println!("{}", self.render_contributors(&contributors));
+
+        Ok(())
+    }
+}
+
+async fn collect_repo_contributions(
+    owner: String,
+    repo: String,
+    opts: RepoReportOptions,
+) -> anyhow::Result<RepoContributions> {
+    let changelog = generate_repo_report(owner.clone(), repo.clone(), opts).await?;
+
+    let prior_emails = if changelog.prev_tag.is_empty() {
+        None
+    } else {
+        let git = GitCli::new(owner.clone(), repo.clone());
+        Some(
+            git.log(&changelog.prev_tag, "%ae")?
+                .into_iter()
+                .map(|it| it.to_lowercase())
+                .collect(),
+        )
+    };
+
+    let mut pull_requests: IndexMap<u64, PullRequestInfo> = IndexMap::new();
+    for commit in &changelog.commits {
+        let prs = match github_client()
+            .commits(&owner, &repo)
+            .associated_pull_requests(PullRequestTarget::Sha(commit.sha.clone()))
+            .send()
+            .await
+        {
+            Ok(page) => page.items,
+            Err(err) => {
+                log::warn!(
+                    "Failed to find the pull requests of {}@{}: {:?}",
+                    changelog.repo_ref,
+                    commit.sha,
+                    err
+                );
+                continue;
+            }
+        };
+
+        for pr in prs {
+            if pull_requests.contains_key(&pr.number) {
+                continue;
+            }
+
+            let reviewers = match list_reviewers(&owner, &repo, pr.number).await {
+                Ok(reviewers) => reviewers,
+                Err(err) => {
+                    log::warn!(
+                        "Failed to find the reviewers of {}#{}: {:?}",
+                        changelog.repo_ref,
+                        pr.number,
+                        err
+                    );
+                    IndexSet::new()
+                }
+            };
+
+            pull_requests.insert(
+                pr.number,
+                PullRequestInfo {
+                    author: pr.user.map(|it| it.login),
+                    reviewers: reviewers.into_iter().collect(),
+                },
+            );
+        }
+    }
+
+    Ok(RepoContributions {
+        commits: changelog.commits,
+        pull_requests: pull_requests.into_values().collect(),
+        prior_emails,
+    })
+}
+
+async fn list_reviewers(owner: &str, repo: &str, number: u64) -> anyhow::Result<IndexSet<String>> {
+    let reviews = github_client()
+        .pulls(owner, repo)
+        .list_reviews(number)
+        .per_page(100)
+        .send()
+        .await?;
+
+    // A reviewer may review a PR several times, interleaved with other reviewers
+    Ok(github_client()
+        .all_pages(reviews)
+        .await?
+        .into_iter()
+        .filter_map(|it| it.user.map(|user| user.login))
+        .collect())
+}
+
+impl ContributorsArgs {
+    fn render_contributors(&self, contributors: &[Contributor]) -> String {
+        let mut str = format!(
+            "## Contributors {}\n| Contributor | Commits | Co-authored Commits | Pull Requests | Reviews | Issues |\n|---|---|---|---|---|---|\n",
+            self.tag.as_deref().unwrap_or(&self.branch)
+        );
+
+        for contributor in contributors {
+            str += &format!(
+                "| {}{} | {} | {} | {} | {} | {} |\n",
+                contributor.name,
+                if contributor.is_first_time {
+                    " 🎉"
+                } else {
+                    ""
+                },
+                contributor.commits,
+                contributor.co_authored_commits,
+                contributor.pull_requests,
+                contributor.reviews,
+                contributor.issues,
+            );
+        }
+
+        let first_timers: Vec<&str> = contributors
+            .iter()
+            .filter(|it| it.is_first_time)
+            .map(|it| it.name.as_str())
+            .collect();
+        if !first_timers.is_empty() {
+            str += "\n### First-Time Contributors\n";
+            for name in first_timers {
+                str += &format!("- {}\n", name);
+            }
+        }
+
+        str
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashset;
+
+    use super::*;
+
+    #[test]
+    fn test_collect_contributors() {
+        let commit =
+            |login: Option<&str>, name: &str, email: &str, message: &str| ChangelogCommit {
+                author_name: name.to_string(),
+                author_email: email.to_string(),
+                ..ChangelogCommit::for_test("0123456789abcdef", message, login)
+            };
+
+        let repos = vec![RepoContributions {
+            commits: vec![
+                commit(Some("dev1"), "Dev 1", "dev1@example.com", "fix: foo"),
+                commit(
+                    None,
+                    "Dev 2",
+                    "1234+dev2@users.noreply.github.com",
+                    "feat: bar\n\nCo-authored-by: Dev 1 <dev1@example.com>\nCo-authored-by: Dev 3 <dev3@example.com>",
+                ),
+                commit(Some("maintainer"), "Maintainer", "m@example.com", "chore: baz"),
+            ],
+            pull_requests: vec![PullRequestInfo {
+                author: Some("dev2".to_string()),
+                reviewers: vec![
+                    "dev2".to_string(),
+                    "maintainer".to_string(),
+                    "dev1".to_string(),
+                ],
+            }],
+            prior_emails: Some(hashset! {"dev1@example.com".to_string()}),
+        }];
+
+        let contributors = collect_contributors(
+            &repos,
+            &["dev1".to_string(), "renovate[bot]".to_string()],
+            &["maintainer".to_string()],
+        );
+
+        let stats: Vec<(&str, usize, usize, usize, usize, usize, bool)> = contributors
+            .iter()
+            .map(|it| {
+                (
+                    it.name.as_str(),
+                    it.commits,
+                    it.co_authored_commits,
+                    it.pull_requests,
+                    it.reviews,
+                    it.issues,
+                    it.is_first_time,
+                )
+            })
+            .collect();
+        assert_eq!(
+            stats,
+            vec![
+                ("Dev 3", 0, 1, 0, 0, 0, true),
+                ("@dev1", 1, 1, 0, 1, 1, false),
+                ("@dev2", 1, 0, 1, 0, 0, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::cmds::{CliCommand, OutputFormat};
+use crate::crd::{diff_crds, parse_crds, CrdDiff};
+use crate::git::{show_file_or_empty, GitCli, GitOperationTrait};
+use crate::Cli;
+
+#[derive(Args)]
+#[command(about = "Show CRD changes of the deploy manifest between releases")]
+pub struct CrdDiffArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repo")]
+    repo: String,
+
+    #[arg(
+        long,
+        default_value = "master",
+        help = "Branch to clone, the tags are read from the clone"
+    )]
+    branch: String,
+
+    #[arg(long, help = "Tag of the previous release")]
+    from: String,
+
+    #[arg(long, help = "Tag of the release")]
+    to: String,
+
+    #[arg(
+        long,
+        default_value = "deploy/longhorn.yaml",
+        help = "Deploy manifest including the CRDs"
+    )]
+    manifest: String,
+
+    #[arg(long, value_enum, default_value_t, help = "Output format")]
+    output: OutputFormat,
+}
+
+#[async_trait]
+impl CliCommand for CrdDiffArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let git = GitCli::new(self.owner.clone(), self.repo.clone());
+        git.clone_repo(&self.branch)?;
+
+        let read = |tag: &str| show_file_or_empty(git.repo.repo_dir_path(), tag, &self.manifest);
+
+        let diff = diff_crds(
+            &parse_crds(&read(&self.from)?)?,
+            &parse_crds(&read(&self.to)?)?,
+        );
+
+        match self.output {
+            OutputFormat::Markdown => println!(
+                "{}",
+                render_crd_diff(git.repo.repo_ref(), &self.from, &self.to, &diff)
+            ),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        }
+
+        Ok(())
+    }
+}
+
+fn render_crd_diff(repo_ref: &str, from: &str, to: &str, diff: &CrdDiff) -> String {
+    let mut str = format!("## CRD Changes {} {} → {}\n", repo_ref, from, to);
+
+    if diff.added_crds.is_empty() && diff.removed_crds.is_empty() && diff.changed_crds.is_empty() {
+        return str + "No changes\n";
+    }
+
+    for (title, crds) in [
+        ("Added CRDs", &diff.added_crds),
+        ("Removed CRDs", &diff.removed_crds),
+    ] {
+        if !crds.is_empty() {
+            str += &format!("\n### {title}\n");
+            for crd in crds {
+                str += &format!("- `{crd}`\n");
+            }
+        }
+    }
+
+    for change in &diff.changed_crds {
+        str += &format!("\n### {}\n", change.name);
+
+        if !change.added_versions.is_empty() {
+            str += &format!("- Added versions: {}\n", change.added_versions.join(", "));
+        }
+        if !change.removed_versions.is_empty() {
+            str += &format!(
+                "- Removed versions: {}\n",
+                change.removed_versions.join(", ")
+            );
+        }
+        if change.is_storage_version_changed() {
+            str += &format!(
+                "- Storage version: {} → {}\n",
+                change.prev_storage_version.as_deref().unwrap_or("-"),
+                change.storage_version.as_deref().unwrap_or("-")
+            );
+        }
+
+        for field in &change.fields {
+            str += &match (&field.from, &field.to) {
+                (None, Some(to)) => {
+                    format!("- {}: added `{}` ({})\n", field.version, field.field, to)
+                }
+                (Some(from), None) => {
+                    format!(
+                        "- {}: removed `{}` ({})\n",
+                        field.version, field.field, from
+                    )
+                }
+                (Some(from), Some(to)) => format!(
+                    "- {}: retyped `{}`: {} → {}\n",
+                    field.version, field.field, from, to
+                ),
+                (None, None) => String::new(),
+            };
+        }
+    }
+
+    str
+}
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde::Serialize;
+
+use crate::cmds::{CliCommand, OutputFormat};
+use crate::git::{show_file_or_empty, GitCli, GitOperationTrait};
+use crate::manifest::{diff_values, flatten_yaml, parse_image_list, ValueChange};
+use crate::Cli;
+
+const IMAGE_LIST_PATH: &str = "deploy/longhorn-images.txt";
+const CHART_VALUES_PATH: &str = "chart/values.yaml";
+const CHART_PATH: &str = "chart/Chart.yaml";
+const CHART_VERSION_KEYS: [&str; 2] = ["version", "appVersion"];
+
+#[derive(Args)]
+#[command(about = "Show image and chart changes of a repo between releases")]
+pub struct DiffArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repo")]
+    repo: String,
+
+    #[arg(
+        long,
+        default_value = "master",
+        help = "Branch to clone, the tags are read from the clone"
+    )]
+    branch: String,
+
+    #[arg(long, help = "Tag of the previous release")]
+    from: String,
+
+    #[arg(long, help = "Tag of the release")]
+    to: String,
+
+    #[arg(long, value_enum, default_value_t, help = "Output format")]
+    output: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct ReleaseDiff {
+    repo: String,
+    from: String,
+    to: String,
+    images: Vec<ValueChange>,
+    values: Vec<ValueChange>,
+    chart: Vec<ValueChange>,
+}
+
+#[async_trait]
+impl CliCommand for DiffArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let git = GitCli::new(self.owner.clone(), self.repo.clone());
+        git.clone_repo(&self.branch)?;
+
+        let read = |tag: &str, path: &str| show_file_or_empty(git.repo.repo_dir_path(), tag, path);
+
+        let images = diff_values(
+            &parse_image_list(&read(&self.from, IMAGE_LIST_PATH)?),
+            &parse_image_list(&read(&self.to, IMAGE_LIST_PATH)?),
+        );
+        let values = diff_values(
+            &flatten_yaml(&read(&self.from, CHART_VALUES_PATH)?)?,
+            &flatten_yaml(&read(&self.to, CHART_VALUES_PATH)?)?,
+        );
+        let mut chart = diff_values(
+            &flatten_yaml(&read(&self.from, CHART_PATH)?)?,
+            &flatten_yaml(&read(&self.to, CHART_PATH)?)?,
+        );
+        chart.retain(|it| CHART_VERSION_KEYS.contains(&it.key.as_str()));
+
+        let diff = ReleaseDiff {
+            repo: git.repo.repo_ref().clone(),
+            from: self.from.clone(),
+            to: self.to.clone(),
+            images,
+            values,
+            chart,
+        };
+
+        match self.output {
+            OutputFormat::Markdown => println!("{}", render_release_diff(&diff)),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        }
+
+        Ok(())
+    }
+}
+
+fn render_value_changes(
+    title: &str,
+    header: &str,
+    diff: &ReleaseDiff,
+    changes: &[ValueChange],
+) -> String {
+    let mut str = format!("\n### {title}\n");
+
+    if changes.is_empty() {
+        return str + "No changes\n";
+    }
+
+    str += &format!(
+        "| {} | {} | {} |\n|---|---|---|\n",
+        header, diff.from, diff.to
+    );
+    for change in changes {
+        str += &format!(
+            "| `{}` | {} | {} |\n",
+            change.key,
+            render_value(&change.from),
+            render_value(&change.to)
+        );
+    }
+
+    str
+}
+
+fn render_value(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("`{}`", value.replace('|', "\\|")),
+        None => "-".to_string(),
+    }
+}
+
+fn render_release_diff(diff: &ReleaseDiff) -> String {
+    let mut str = format!("## {} {} → {}\n", diff.repo, diff.from, diff.to);
+
+    str += &render_value_changes("Images", "Image", diff, &diff.images);
+    str += &render_value_changes("Chart Values", "Key", diff, &diff.values);
+    str += &render_value_changes("Chart", "Key", diff, &diff.chart);
+
+    str
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use clap::Args;
+use glob::Pattern;
+use indexmap::IndexSet;
+use regex::Regex;
+use serde::Serialize;
+use tracing_log::log;
+
+use crate::cmds::pr::longhorn_version_rules;
+use crate::cmds::{CliCommand, OutputFormat};
+use crate::config::Config;
+use crate::git::{GitCli, GitOperationTrait};
+use crate::manifest::{check_image_refs, find_image_refs, parse_image_list, ImageRef};
+use crate::registry::{split_image, RegistryClient};
+use crate::Cli;
+
+/// Files referencing images besides the files of the version rules.
+const IMAGE_FILES: [&str; 2] = ["deploy/longhorn.yaml", "deploy/longhorn-images.txt"];
+
+#[derive(Args)]
+#[command(about = "Check the image references of a repo are consistent at a tag")]
+pub struct CheckImagesArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repo")]
+    repo: String,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, help = "Tag")]
+    tag: String,
+
+    #[arg(
+        long,
+        default_value = "longhornio/",
+        help = "Prefix of the checked images"
+    )]
+    image_prefix: String,
+
+    #[arg(
+        long,
+        default_value = r"^longhornio/(longhorn-[\w-]+|backing-image-manager)$",
+        help = "Regex of the images tagged with the release tag"
+    )]
+    release_images: String,
+
+    #[arg(long, help = "Extra files to check (support glob)")]
+    files: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t, help = "Output format")]
+    output: OutputFormat,
+}
+
+#[derive(Args)]
+#[command(about = "Check the images of the image list at a tag exist in the registry")]
+pub struct CheckRegistryArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repo")]
+    repo: String,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, help = "Tag")]
+    tag: String,
+
+    #[arg(
+        long,
+        default_value = "deploy/longhorn-images.txt",
+        help = "Image list file in the repo"
+    )]
+    images_file: String,
+
+    #[arg(
+        long,
+        default_value = "https://registry-1.docker.io",
+        help = "Registry endpoint of the images without a registry host"
+    )]
+    registry: String,
+
+    #[arg(long, value_enum, default_value_t, help = "Output format")]
+    output: OutputFormat,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+struct RegistryImage {
+    image: String,
+    tag: String,
+    digest: Option<String>,
+}
+
+#[async_trait]
+impl CliCommand for CheckRegistryArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let git = GitCli::new(self.owner.clone(), self.repo.clone());
+        git.clone_repo(&self.branch)?;
+
+        let content = git.show_file(&self.tag, &self.images_file)?;
+        let images = parse_image_list(&content);
+        if images.is_empty() {
+            return Err(anyhow!("no images found in {}", self.images_file));
+        }
+
+        let mut default_client = RegistryClient::new(&self.registry);
+        let mut clients: BTreeMap<&str, RegistryClient> = BTreeMap::new();
+
+        let mut results = vec![];
+        for (image, tag) in &images {
+            log::info!("Resolving {}:{}", image, tag);
+
+            let (host, repository) = split_image(image);
+            let client = match host {
+                Some(host) => clients
+                    .entry(host)
+                    .or_insert_with(|| RegistryClient::new(&format!("https://{}", host))),
+                None => &mut default_client,
+            };
+
+            results.push(RegistryImage {
+                image: image.clone(),
+                tag: tag.clone(),
+                digest: client.manifest_digest(&repository, tag).await?,
+            });
+        }
+
+        match self.output {
+            OutputFormat::Markdown => println!("{}", render_registry_images(&self.tag, &results)),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+        }
+
+        let missing = results.iter().filter(|it| it.digest.is_none()).count();
+        if missing > 0 {
+            return Err(anyhow!("found {} missing images", missing));
+        }
+
+        Ok(())
+    }
+}
+
+fn render_registry_images(tag: &str, images: &[RegistryImage]) -> String {
+    let mut str = format!(
+        "## Registry Images of {}\n| Image | Tag | Digest |\n|---|---|---|\n",
+        tag
+    );
+
+    for image in images {
+        str += &format!(
+            "| {} | {} | {} |\n",
+            image.image,
+            image.tag,
+            image.digest.as_deref().unwrap_or("⚠️ missing")
+        );
+    }
+
+    str
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+struct ImageCheck {
+    repo: String,
+    tag: String,
+    refs: Vec<ImageRef>,
+    problems: Vec<String>,
+}
+
+#[async_trait]
+impl CliCommand for CheckImagesArgs {
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        let config = Config::load(cli.config.as_deref())?;
+        let release_images = Regex::new(&self.release_images)?;
+
+        let mut globs: IndexSet<String> = config
+            .version_rules
+            .iter()
+            .chain(&longhorn_version_rules(&[]))
+            .map(|it| it.files.clone())
+            .collect();
+        globs.extend(IMAGE_FILES.iter().map(|it| it.to_string()));
+        globs.extend(self.files.iter().cloned());
+        let patterns = globs
+            .iter()
+            .map(|it| Pattern::new(it))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let git = GitCli::new(self.owner.clone(), self.repo.clone());
+        git.clone_repo(&self.branch)?;
+
+        let mut refs = vec![];
+        for file in git.list_files(&self.tag)? {
+            if !patterns.iter().any(|it| it.matches(&file)) {
+                continue;
+            }
+
+            log::info!("Checking images of {} at {}", file, self.tag);
+            let content = git.show_file(&self.tag, &file)?;
+            refs.extend(find_image_refs(&file, &content, &self.image_prefix));
+        }
+
+        let check = ImageCheck {
+            repo: git.repo.repo_ref().clone(),
+            tag: self.tag.clone(),
+            problems: check_image_refs(&refs, &self.tag, &release_images),
+            refs,
+        };
+
+        match self.output {
+            OutputFormat::Markdown => println!("{}", render_image_check(&check)),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&check)?),
+        }
+
+        if !check.problems.is_empty() {
+            return Err(anyhow!(
+                "found {} image reference problems",
+                check.problems.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn render_image_check(check: &ImageCheck) -> String {
+    let mut str = format!(
+        "## Images of {} {}\n| Image | Tag | Files |\n|---|---|---|\n",
+        check.repo, check.tag
+    );
+
+    let mut files_by_image: BTreeMap<(&str, &str), BTreeSet<&str>> = BTreeMap::new();
+    for r in &check.refs {
+        files_by_image
+            .entry((&r.image, &r.tag))
+            .or_default()
+            .insert(&r.file);
+    }
+
+    for ((image, tag), files) in files_by_image {
+        str += &format!(
+            "| {} | {} | {} |\n",
+            image,
+            tag,
+            files.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    str += "\n### Problems\n";
+    if check.problems.is_empty() {
+        str += "No problems\n";
+    }
+    for problem in &check.problems {
+        str += &format!("- ⚠️ {}\n", problem);
+    }
+
+    str
+}
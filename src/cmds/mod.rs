@@ -1,9 +1,21 @@
+use std::future::Future;
+
 use async_trait::async_trait;
+use clap::ValueEnum;
+use tokio::runtime::Handle;
 
 use crate::Cli;
 
+pub mod audit;
 pub mod changelog;
+pub mod chart;
+pub mod contributors;
+pub mod crd;
+pub mod diff;
+pub mod images;
+pub mod pending;
 pub mod pr;
+pub mod reconcile;
 pub mod release;
 pub mod tag;
 
@@ -11,3 +23,41 @@ pub mod tag;
 pub trait CliCommand {
     async fn run(&self, cli: &Cli) -> anyhow::Result<()>;
 }
+
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+/// Runs the task of each repo concurrently, and returns the results in the order of the repos.
+///
+/// The tasks run on the blocking threads, because cloning and reading the repos block.
+pub(crate) async fn run_repo_tasks<T, F, Fut>(
+    owner: &str,
+    repos: &[String],
+    task: F,
+) -> anyhow::Result<Vec<T>>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let mut task_joiner = tokio::task::JoinSet::new();
+
+    for (index, repo) in repos.iter().enumerate() {
+        let future = task(owner.to_string(), repo.clone());
+        let handle = Handle::current();
+
+        task_joiner.spawn_blocking(move || (index, handle.block_on(future)));
+    }
+
+    let mut results = vec![];
+    while let Some(res) = task_joiner.join_next().await {
+        results.push(res?);
+    }
+    results.sort_by_key(|(index, _)| *index);
+
+    Ok(results.into_iter().map(|(_, it)| it).collect())
+}
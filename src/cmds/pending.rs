@@ -0,0 +1,241 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use indexmap::IndexSet;
+use tracing_log::log;
+
+use crate::cmds::{run_repo_tasks, CliCommand};
+use crate::git::{GitCli, GitOperationTrait};
+use crate::Cli;
+
+#[derive(Args)]
+#[command(about = "Show unreleased changes of repos on a branch")]
+pub struct PendingArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(long, help = "GitHub repos")]
+    repos: Vec<String>,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, help = "Fail if any repo has more unreleased commits than this")]
+    max_commits: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Fail if the oldest unreleased commit of any repo is older than these days"
+    )]
+    max_age_days: Option<i64>,
+}
+
+/// The unreleased changes of a repo since the latest tag on the branch.
+#[derive(Clone, Debug, Default)]
+struct PendingRelease {
+    repo_ref: String,
+    latest_tag: Option<String>,
+    commits: usize,
+    authors: IndexSet<String>,
+    oldest_commit_at: Option<DateTime<Utc>>,
+}
+
+impl PendingRelease {
+    /// Creates from the lines of `git log --format=%at%x09%an` ordered from newest to oldest.
+    fn from_log(repo_ref: String, latest_tag: Option<String>, lines: &[String]) -> Self {
+        let mut pending = Self {
+            repo_ref,
+            latest_tag,
+            commits: lines.len(),
+            ..Default::default()
+        };
+
+        for line in lines {
+            let (timestamp, author) = line.split_once('\t').unwrap_or((line, ""));
+
+            pending.authors.insert(author.to_string());
+            pending.oldest_commit_at = timestamp
+                .parse::<i64>()
+                .ok()
+                .and_then(|it| DateTime::from_timestamp(it, 0))
+                .or(pending.oldest_commit_at);
+        }
+
+        pending
+    }
+
+    fn age_days(&self) -> Option<i64> {
+        self.oldest_commit_at.map(|it| (Utc::now() - it).num_days())
+    }
+}
+
+#[async_trait]
+impl CliCommand for PendingArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let pendings = run_repo_tasks(&self.owner, &self.repos, |owner, repo| {
+            let branch = self.branch.clone();
+            async move { find_pending_release(owner, repo, branch) }
+        })
+        .await?
+        .into_iter()
+        .collect::<anyhow::Result<Vec<PendingRelease>>>()?;
+
+        println!("{}", self.render_pending_releases(&pendings));
+
+        let overdue: Vec<&str> = pendings
+            .iter()
+            .filter(|it| self.is_past_threshold(it))
+            .map(|it| it.repo_ref.as_str())
+            .collect();
+        if !overdue.is_empty() {
+            return Err(anyhow!(
+                "repos have unreleased changes past the threshold: {}",
+                overdue.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn find_pending_release(
+    owner: String,
+    repo: String,
+    branch: String,
+) -> anyhow::Result<PendingRelease> {
+    let git = GitCli::new(owner, repo);
+    git.clone_repo(&branch)?;
+
+    let latest_tag = match git.latest_tag(&branch) {
+        Ok(tag) => Some(tag),
+        Err(err) => {
+            log::debug!(
+                "Failed to find the latest tag of {}: {:?}",
+                git.repo.repo_ref(),
+                err
+            );
+            None
+        }
+    };
+
+    let range = match &latest_tag {
+        Some(tag) => format!("{tag}..{branch}"),
+        None => branch.clone(),
+    };
+    let lines = git.log(&range, "%at%x09%an")?;
+
+    Ok(PendingRelease::from_log(
+        git.repo.repo_ref().clone(),
+        latest_tag,
+        &lines,
+    ))
+}
+
+impl PendingArgs {
+    fn is_past_threshold(&self, pending: &PendingRelease) -> bool {
+        if pending.commits == 0 {
+            return false;
+        }
+
+        self.max_commits.is_some_and(|max| pending.commits > max)
+            || self
+                .max_age_days
+                .zip(pending.age_days())
+                .is_some_and(|(max, age)| age > max)
+    }
+
+    fn render_pending_releases(&self, pendings: &[PendingRelease]) -> String {
+        let mut str = format!(
+            "## Unreleased Changes on {}\n| Repo | Latest Tag | Commits | Authors | Oldest Commit |\n|---|---|---|---|---|\n",
+            self.branch
+        );
+
+        for pending in pendings {
+            str += &format!(
+                "| {}{} | {} | {} | {} | {} |\n",
+                pending.repo_ref,
+                if self.is_past_threshold(pending) {
+                    " ⚠️"
+                } else {
+                    ""
+                },
+                pending.latest_tag.as_deref().unwrap_or("-"),
+                pending.commits,
+                pending
+                    .authors
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                pending
+                    .age_days()
+                    .map(|it| format!("{it} days ago"))
+                    .unwrap_or("-".to_string()),
+            );
+        }
+
+        str
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_release_from_log() {
+        let lines = vec![
+            "1704153600\tdev1".to_string(),
+            "1704067200\tdev2".to_string(),
+            "1703980800\tdev1".to_string(),
+        ];
+
+        let pending = PendingRelease::from_log(
+            "longhorn/longhorn-manager".to_string(),
+            Some("v1.6.0".to_string()),
+            &lines,
+        );
+
+        assert_eq!(pending.commits, 3);
+        assert_eq!(
+            pending.authors.iter().collect::<Vec<_>>(),
+            vec!["dev1", "dev2"]
+        );
+        assert_eq!(
+            pending.oldest_commit_at,
+            DateTime::from_timestamp(1703980800, 0)
+        );
+    }
+
+    #[test]
+    fn test_is_past_threshold() {
+        let args = |max_commits, max_age_days| PendingArgs {
+            owner: "longhorn".to_string(),
+            repos: vec![],
+            branch: "master".to_string(),
+            max_commits,
+            max_age_days,
+        };
+        let pending = PendingRelease {
+            commits: 3,
+            oldest_commit_at: Some(Utc::now() - chrono::Duration::days(10)),
+            ..Default::default()
+        };
+
+        let data = vec![
+            (None, None, false),
+            (Some(3), None, false),
+            (Some(2), None, true),
+            (None, Some(10), false),
+            (None, Some(9), true),
+        ];
+
+        for (max_commits, max_age_days, expected) in data {
+            assert_eq!(
+                args(max_commits, max_age_days).is_past_threshold(&pending),
+                expected
+            );
+        }
+    }
+}
@@ -1,37 +1,26 @@
-use std::collections::HashMap;
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use clap::Args;
-use lazy_static::lazy_static;
-use maplit::hashmap;
+use glob::{glob, Pattern};
+use indexmap::{IndexMap, IndexSet};
 use regex::Regex;
 use tracing_log::log;
 
+use crate::cmds::chart::write_deploy_manifest;
 use crate::cmds::CliCommand;
 use crate::common::execute;
-use crate::git::{GitCli, GitOperationTrait};
-use crate::github::{GithubCli, GithubOperationTrait};
-use crate::{cmd, Cli};
-
-lazy_static! {
-    static ref VERSION_MANIFEST_PATTERNS: HashMap<&'static str, Vec<&'static str>> = hashmap! {
-        "chart/Chart.yaml" => vec![
-            r"(version: )(\S+)",
-            r"(appVersion: v?)(\S+)"
-        ],
-        "chart/questions.yaml" => vec![
-            r"(variable: image\.longhorn\.(manager|engine|ui|instanceManager|shareManager|backingImageManager)\.tag[\s\S]*?default:\s+)(\S+)",
-        ],
-        "chart/values.yaml" => vec![
-            r"(repository: longhornio\/(longhorn|backing)[\s\S]*?tag:\s+)(\S+)"
-        ],
-        "uninstall/uninstall.yaml" => vec![
-            r"(image: longhornio/\S+:)(\S+)"
-        ]
-    };
-}
+use crate::config::{Config, DeployManifest, PullRequestConfig, VersionRule};
+use crate::git::{ChangedFile, GitCli, GitOperationTrait};
+use crate::github::{
+    GithubCli, GithubOperationTrait, MergeMethod, MergeReadiness, PullRequestOptions,
+};
+use crate::yamlpath::{get_yaml_path, set_yaml_path};
+use crate::Cli;
 
 //TODO Keep repo but rename it to repos. Remove the original chart_repo and repos to make the command general
 #[derive(Args)]
@@ -55,12 +44,21 @@ pub struct PrArgs {
     #[arg(long, help = "Dry run")]
     dryrun: bool,
 
-    #[arg(long, help = "Merge the created PRs")]
-    merge: bool,
+    #[command(flatten)]
+    merge: MergeArgs,
 
     #[arg(long, help = "Script to update files in repo for PR")]
     hook: Option<String>,
 
+    #[arg(long, help = "Commit the changes without confirmation")]
+    yes: bool,
+
+    #[arg(
+        long,
+        help = "Paths the PRs may touch (support glob), besides the allowed paths of the config"
+    )]
+    allowed_paths: Vec<String>,
+
     #[arg(long, hide = true, help = "Longhorn chart repo")]
     longhorn_chart_repo: Option<String>,
 
@@ -71,7 +69,8 @@ pub struct PrArgs {
 
 #[async_trait]
 impl CliCommand for PrArgs {
-    async fn run(&self, _: &Cli) -> anyhow::Result<()> {
+    async fn run(&self, cli: &Cli) -> anyhow::Result<()> {
+        let config = Config::load(cli.config.as_deref())?;
         let git = GitCli::new(self.owner.clone(), self.repo.clone());
         let repo_dir_path = git.repo.repo_dir_path();
 
@@ -86,23 +85,24 @@ impl CliCommand for PrArgs {
                 .to_string()]),
         )?;
 
-        if self.longhorn_repos.is_some() {
-            let components = get_container_component_names(self.longhorn_repos.as_ref().unwrap());
-            update_version_manifests(repo_dir_path, &self.tag, &components)?;
+        let mut version_rules = config.version_rules.clone();
+        if let Some(longhorn_repos) = self.longhorn_repos.as_ref() {
+            let components = get_container_component_names(longhorn_repos);
+            version_rules.extend(longhorn_version_rules(&components));
         }
+        let version_bumps = apply_version_rules(repo_dir_path, &version_rules, &self.tag)?;
 
-        if self.longhorn_chart_repo.is_some() {
-            let longhorn_chart_repo = self.longhorn_chart_repo.as_ref().unwrap();
+        if let Some(longhorn_chart_repo) = self.longhorn_chart_repo.as_ref() {
             let git = GitCli::new(self.owner.clone(), longhorn_chart_repo.clone());
             git.clone_repo(&self.branch)?;
             let chart_repo_dir_path = git.repo.repo_dir_path();
 
-            update_deploy_manifest(repo_dir_path, chart_repo_dir_path)?;
+            update_deploy_manifest(repo_dir_path, chart_repo_dir_path, &config.deploy_manifest)?;
         }
 
         let mut changed_repos = vec![];
 
-        if self.hook.is_some() || self.longhorn_repos.is_some() {
+        if self.hook.is_some() || !version_rules.is_empty() {
             changed_repos.push((self.owner.clone(), self.repo.clone()));
         }
         if let Some(repo) = self.longhorn_chart_repo.as_ref() {
@@ -114,30 +114,53 @@ impl CliCommand for PrArgs {
             return Ok(());
         }
 
+        let mut pr_options = vec![];
+        for (owner, repo) in &changed_repos {
+            let pr_config = config.pull_request(repo);
+            let mut allowed_paths = self.allowed_paths.clone();
+            allowed_paths.extend(pr_config.allowed_paths.iter().cloned());
+
+            let git = GitCli::new(owner.clone(), repo.clone());
+            let files = review_changes(&git, &allowed_paths, self.yes || self.dryrun)?;
+
+            let bumps = match *repo == self.repo {
+                true => version_bumps.as_slice(),
+                false => &[],
+            };
+            let body = render_pr_body(
+                &self.tag,
+                &changelog_url(&pr_config, owner, repo, &self.tag),
+                &files,
+                bumps,
+            );
+            pr_options.push(pull_request_options(
+                &config,
+                owner,
+                repo,
+                &self.tag,
+                Some(body),
+            ));
+        }
+
         //TODO if nothing changed, also there is no need to create a PR
         if !self.dryrun {
             // let mut task_joiner = tokio::task::JoinSet::new();
-            for (owner, repo) in changed_repos {
+            for ((owner, repo), options) in changed_repos.into_iter().zip(pr_options) {
                 let message = self.message.clone().unwrap_or_default();
                 let tag = self.tag.clone();
                 let branch = self.branch.clone();
-                let merge = self.merge;
 
                 // task_joiner.spawn(async move {
                 let gh_client = GithubCli::new(owner, repo);
 
-                gh_client
-                    .create_pr(&message, &tag, &branch)
-                    .and_then(|id| match id {
-                        id if id.is_empty() => Ok(()),
-                        _ => {
-                            if merge {
-                                gh_client.merge_pr(id.trim())
-                            } else {
-                                Ok(())
-                            }
-                        }
-                    })?;
+                let id = gh_client.create_pr(
+                    &message,
+                    &tag,
+                    &branch,
+                    &format!("pr-{}", tag),
+                    &options,
+                )?;
+                self.merge.merge_when_ready(&gh_client, id.trim()).await?;
                 // });
             }
 
@@ -150,6 +173,259 @@ impl CliCommand for PrArgs {
     }
 }
 
+/// Options to merge the created PRs after their checks and reviews pass.
+#[derive(Args)]
+pub(crate) struct MergeArgs {
+    #[arg(long, help = "Merge the created PRs after the checks and reviews pass")]
+    merge: bool,
+
+    #[arg(long, value_enum, default_value_t, help = "Merge method")]
+    merge_method: MergeMethod,
+
+    #[arg(
+        long,
+        requires = "merge",
+        help = "Merge with admin override of the branch protection, without waiting for reviews"
+    )]
+    admin: bool,
+
+    #[arg(
+        long,
+        default_value_t = 60,
+        help = "Minutes to wait for the checks and reviews before merging"
+    )]
+    merge_timeout: u64,
+
+    #[arg(
+        long,
+        default_value_t = 30,
+        help = "Seconds between polls of the checks and reviews"
+    )]
+    merge_poll_interval: u64,
+
+    #[arg(
+        long,
+        default_value_t = 300,
+        help = "Seconds to wait for any check to be reported before merging a PR without checks"
+    )]
+    checks_grace_period: u64,
+}
+
+impl MergeArgs {
+    /// Polls the checks and reviews of the PR until they pass, fail or time out, then merges
+    /// the PR if merging is enabled.
+    pub(crate) async fn merge_when_ready(
+        &self,
+        gh_client: &GithubCli,
+        id: &str,
+    ) -> anyhow::Result<()> {
+        if !self.merge || id.is_empty() {
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(self.merge_timeout * 60);
+        loop {
+            let expect_checks = started.elapsed() < Duration::from_secs(self.checks_grace_period);
+            match gh_client
+                .pr_status(id)?
+                .readiness(!self.admin, expect_checks)
+            {
+                MergeReadiness::Ready => break,
+                MergeReadiness::Failed(reasons) => {
+                    return Err(anyhow!(
+                        "PR {} cannot be merged: {}",
+                        id,
+                        reasons.join(", ")
+                    ));
+                }
+                MergeReadiness::Pending(reasons) => {
+                    if Instant::now() >= deadline {
+                        return Err(anyhow!(
+                            "timed out waiting for PR {}: {}",
+                            id,
+                            reasons.join(", ")
+                        ));
+                    }
+
+                    log::info!("Waiting for PR {}: {}", id, reasons.join(", "));
+                    tokio::time::sleep(Duration::from_secs(self.merge_poll_interval)).await;
+                }
+            }
+        }
+
+        let sha = gh_client.merge_pr(id, self.merge_method, self.admin)?;
+        println!("Merged PR {} as {}", id, sha);
+
+        Ok(())
+    }
+}
+
+/// Shows the changed files and the diff of the repo, checks the files are allowed, and asks
+/// for the confirmation to commit them unless confirmed. Returns the changed files.
+fn review_changes(
+    git: &GitCli,
+    allowed_paths: &[String],
+    confirmed: bool,
+) -> anyhow::Result<Vec<ChangedFile>> {
+    let files = git.changed_files()?;
+    if files.is_empty() {
+        return Ok(files);
+    }
+
+    println!(
+        "{}",
+        render_changes(git.repo.repo_ref(), &files, &git.diff()?)
+    );
+
+    let disallowed = disallowed_files(&files, allowed_paths)?;
+    if !disallowed.is_empty() {
+        return Err(anyhow!(
+            "{} touches paths not allowed: {}",
+            git.repo.repo_ref(),
+            disallowed
+                .iter()
+                .map(|it| it.path.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    if confirmed {
+        return Ok(files);
+    }
+
+    eprint!("Commit the changes of {}? [y/N] ", git.repo.repo_ref());
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(files),
+        _ => Err(anyhow!(
+            "aborted committing the changes of {}",
+            git.repo.repo_ref()
+        )),
+    }
+}
+
+/// Returns the changed files not matching any allowed path. Any file is allowed if there are
+/// no allowed paths.
+fn disallowed_files<'a>(
+    files: &'a [ChangedFile],
+    allowed_paths: &[String],
+) -> anyhow::Result<Vec<&'a ChangedFile>> {
+    if allowed_paths.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let patterns = allowed_paths
+        .iter()
+        .map(|it| Pattern::new(it))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(files
+        .iter()
+        .filter(|file| !patterns.iter().any(|it| it.matches(&file.path)))
+        .collect())
+}
+
+fn render_changes(repo: &str, files: &[ChangedFile], diff: &str) -> String {
+    let mut str = format!("## Changes of {}\n| Status | File |\n|---|---|\n", repo);
+    for file in files {
+        str += &format!("| {} | {} |\n", file.status, file.path);
+    }
+
+    str + &format!("\n```diff\n{}```\n", diff)
+}
+
+/// Returns the options of a PR of the repo from the config.
+pub(crate) fn pull_request_options(
+    config: &Config,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    body: Option<String>,
+) -> PullRequestOptions {
+    let pr_config = config.pull_request(repo);
+
+    let mut reviewers = pr_config.reviewers.clone();
+    reviewers.extend(
+        pr_config
+            .team_reviewers
+            .iter()
+            .map(|team| match team.contains('/') {
+                true => team.clone(),
+                false => format!("{}/{}", owner, team),
+            }),
+    );
+
+    PullRequestOptions {
+        body,
+        labels: pr_config.labels,
+        reviewers,
+        assignees: pr_config.assignees,
+        milestone: pr_config.milestone.map(|it| it.replace("{version}", tag)),
+    }
+}
+
+/// The Longhorn release, which has the changelog of all the repos, e.g. the charts repo.
+const DEFAULT_CHANGELOG_URL: &str = "https://github.com/longhorn/longhorn/releases/tag/{version}";
+
+fn changelog_url(pr_config: &PullRequestConfig, owner: &str, repo: &str, tag: &str) -> String {
+    pr_config
+        .changelog_url
+        .as_deref()
+        .unwrap_or(DEFAULT_CHANGELOG_URL)
+        .replace("{owner}", owner)
+        .replace("{repo}", repo)
+        .replace("{version}", tag)
+}
+
+fn render_pr_body(
+    tag: &str,
+    changelog_url: &str,
+    files: &[ChangedFile],
+    bumps: &[RuleMatch],
+) -> String {
+    let mut str = format!(
+        "## Release {}\n\nChangelog: {}\n\n### Files Changed\n",
+        tag, changelog_url
+    );
+    for file in files {
+        str += &format!("- `{}`\n", file.path);
+    }
+
+    let bumps: Vec<(&str, Vec<&str>, &str)> = bumps
+        .iter()
+        .filter_map(|m| {
+            let old_values: IndexSet<&str> = m
+                .old_values
+                .iter()
+                .map(|it| it.as_str())
+                .filter(|it| *it != m.new_value)
+                .collect();
+
+            (!old_values.is_empty()).then(|| {
+                (
+                    m.file.as_str(),
+                    old_values.into_iter().collect(),
+                    m.new_value.as_str(),
+                )
+            })
+        })
+        .collect();
+
+    if !bumps.is_empty() {
+        str += "\n### Version Bumps\n| File | Old | New |\n|---|---|---|\n";
+        for (file, old_values, new_value) in bumps {
+            str += &format!("| {} | {} | {} |\n", file, old_values.join(", "), new_value);
+        }
+    }
+
+    str
+}
+
 fn get_container_component_names(repos: &Vec<String>) -> Vec<String> {
     let mut components = vec![];
 
@@ -164,45 +440,226 @@ fn get_container_component_names(repos: &Vec<String>) -> Vec<String> {
     components
 }
 
-fn update_version_manifests(
+/// The version rules of the Longhorn repo, updating the images of the components.
+pub(crate) fn longhorn_version_rules(components: &[String]) -> Vec<VersionRule> {
+    let rule = |files: &str, pattern: &str, value: &str| VersionRule {
+        files: files.to_string(),
+        pattern: Some(pattern.to_string()),
+        value: value.to_string(),
+        ..Default::default()
+    };
+    let path_rule = |files: &str, path: String| VersionRule {
+        files: files.to_string(),
+        path: Some(path),
+        value: "{version}".to_string(),
+        ..Default::default()
+    };
+
+    let mut rules = vec![
+        rule("chart/Chart.yaml", r"(version: )(\S+)", "{version_no_v}"),
+        rule(
+            "chart/Chart.yaml",
+            r"(appVersion: v?)(\S+)",
+            "{version_no_v}",
+        ),
+        rule(
+            "uninstall/uninstall.yaml",
+            r"(image: longhornio/\S+:)(\S+)",
+            "{version}",
+        ),
+    ];
+
+    for image in [
+        "manager",
+        "engine",
+        "ui",
+        "instanceManager",
+        "shareManager",
+        "backingImageManager",
+    ] {
+        rules.push(path_rule(
+            "chart/values.yaml",
+            format!("image.longhorn.{image}.tag"),
+        ));
+        rules.push(path_rule(
+            "chart/questions.yaml",
+            format!("questions[variable=image.defaultImage].subquestions[variable=image.longhorn.{image}.tag].default"),
+        ));
+    }
+
+    for c in components {
+        // Each component must be listed exactly once, so a missing image fails the bump
+        rules.push(VersionRule {
+            expected_matches: Some(1),
+            ..rule(
+                "deploy/longhorn-images.txt",
+                &format!(r"(longhornio/{}:)(\S+)", c),
+                "{version}",
+            )
+        });
+    }
+
+    rules
+}
+
+/// The matches of a version rule in a file.
+#[derive(Clone, Debug, Default)]
+struct RuleMatch {
+    file: String,
+    matcher: String,
+    old_values: Vec<String>,
+    new_value: String,
+}
+
+/// The result of applying version rules, before writing the files.
+#[derive(Clone, Debug, Default)]
+struct VersionUpdates {
+    /// The original and updated contents by file.
+    contents: IndexMap<PathBuf, (String, String)>,
+    matches: Vec<RuleMatch>,
+    mismatches: Vec<String>,
+}
+
+/// Replaces the versions in the files matching the rules, prints the report of matches and
+/// returns them.
+/// Fails without writing any file if a rule does not match as expected.
+fn apply_version_rules(
     repo_dir_path: &Path,
+    rules: &[VersionRule],
     version: &str,
-    components: &[String],
-) -> anyhow::Result<()> {
-    log::info!("Updating manifests");
+) -> anyhow::Result<Vec<RuleMatch>> {
+    if rules.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let updates = plan_version_rules(repo_dir_path, rules, version)?;
 
-    for (f, reg_pats) in VERSION_MANIFEST_PATTERNS.iter() {
-        let mut new_version = version;
+    println!("{}", render_version_updates(&updates));
 
-        // A workaround for chart.yaml
-        if *f == "chart/Chart.yaml" {
-            new_version = version.trim_matches('v');
+    if !updates.mismatches.is_empty() {
+        return Err(anyhow!(
+            "version rules do not match as expected: {}",
+            updates.mismatches.join("; ")
+        ));
+    }
+
+    for (path, (original, updated)) in &updates.contents {
+        if original != updated {
+            log::info!("Updating manifest {:?}", path);
+            fs::write(path, updated)?;
         }
+    }
 
-        let f = repo_dir_path.join(f);
-        let mut str = fs::read_to_string(&f)?;
+    Ok(updates.matches)
+}
 
-        log::info!("Updating manifest {:?}", &f);
+fn plan_version_rules(
+    repo_dir_path: &Path,
+    rules: &[VersionRule],
+    version: &str,
+) -> anyhow::Result<VersionUpdates> {
+    let mut updates = VersionUpdates::default();
+
+    for rule in rules {
+        let matcher = rule.matcher()?;
+        let value = rule.render_value(version)?;
+        let mut total = 0;
+
+        let paths: Vec<PathBuf> =
+            glob(&repo_dir_path.join(&rule.files).to_string_lossy())?.collect::<Result<_, _>>()?;
+
+        for path in paths {
+            if !updates.contents.contains_key(&path) {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {:?}", path))?;
+                updates
+                    .contents
+                    .insert(path.clone(), (content.clone(), content));
+            }
+            let (_, content) = updates.contents.get_mut(&path).unwrap();
+
+            let old_values = match &rule.path {
+                Some(yaml_path) => match get_yaml_path(content, yaml_path) {
+                    Ok(old_value) => {
+                        *content = set_yaml_path(content, yaml_path, &value)?;
+                        vec![old_value]
+                    }
+                    Err(err) => {
+                        log::debug!("Skipped {:?}: {:?}", &path, err);
+                        vec![]
+                    }
+                },
+                None => {
+                    let reg = Regex::new(matcher)?;
+                    let old_values = reg
+                        .captures_iter(content)
+                        .map(|caps| {
+                            let prefix_len = caps.get(1).map_or(0, |it| it.len());
+                            caps[0][prefix_len..].to_string()
+                        })
+                        .collect();
+                    replace_str_with_version_by_reg(content, &[matcher], &value)?;
+                    old_values
+                }
+            };
+
+            if old_values.is_empty() {
+                continue;
+            }
+            total += old_values.len();
+
+            updates.matches.push(RuleMatch {
+                file: path
+                    .strip_prefix(repo_dir_path)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string(),
+                matcher: matcher.to_string(),
+                old_values,
+                new_value: value.clone(),
+            });
+        }
 
-        replace_str_with_version_by_reg(&mut str, reg_pats, new_version)?;
-        fs::write(&f, str)?;
+        if !rule.is_expected(total) {
+            updates.mismatches.push(format!(
+                "{} in {} matches {} times, expected {}",
+                matcher,
+                rule.files,
+                total,
+                rule.expected_matches
+                    .map_or("at least 1".to_string(), |it| it.to_string())
+            ));
+        }
     }
 
-    let f = repo_dir_path.join("deploy").join("longhorn-images.txt");
-    let mut str = fs::read_to_string(&f)?;
+    Ok(updates)
+}
 
-    log::info!("Updating {:?}", &f);
+fn render_version_updates(updates: &VersionUpdates) -> String {
+    let mut str =
+        "## Version Updates\n| File | Rule | Matches | Changes |\n|---|---|---|---|\n".to_string();
+
+    for m in &updates.matches {
+        let old_values: IndexSet<&str> = m.old_values.iter().map(|it| it.as_str()).collect();
+
+        str += &format!(
+            "| {} | `{}` | {} | {} → {} |\n",
+            m.file,
+            m.matcher.replace('|', "\\|"),
+            m.old_values.len(),
+            old_values.into_iter().collect::<Vec<_>>().join(", "),
+            m.new_value
+        );
+    }
 
-    for c in components {
-        replace_str_with_version_by_reg(
-            &mut str,
-            &[&format!(r"(longhornio/{}:)(\S+)", c)],
-            version,
-        )?;
+    if !updates.mismatches.is_empty() {
+        str += "\n### Mismatches\n";
+        for mismatch in &updates.mismatches {
+            str += &format!("- ⚠️ {}\n", mismatch);
+        }
     }
-    fs::write(&f, str)?;
 
-    Ok(())
+    str
 }
 
 fn replace_str_with_version_by_reg(
@@ -224,13 +681,13 @@ fn replace_str_with_version_by_reg(
 fn update_deploy_manifest(
     repo_dir_path: &PathBuf,
     chart_repo_dir_path: &PathBuf,
+    deploy_manifest: &DeployManifest,
 ) -> anyhow::Result<()> {
     log::info!("Updating deploy manifest in {:?}", repo_dir_path);
-    cmd!(
-        "scripts/generate-longhorn-yaml.sh",
-        &repo_dir_path,
-        [] as [&str; 0]
-    );
+    let diff = write_deploy_manifest(repo_dir_path, deploy_manifest)?;
+    if !diff.is_empty() {
+        println!("{}", diff);
+    }
 
     log::info!(
         "Updating chart {:?} from {:?}",
@@ -253,6 +710,9 @@ fn update_deploy_manifest(
 
 #[cfg(test)]
 mod tests {
+    use indoc::indoc;
+    use maplit::hashmap;
+
     use super::*;
 
     #[test]
@@ -283,6 +743,201 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_apply_version_rules() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("chart"))?;
+        fs::write(
+            dir.path().join("chart/Chart.yaml"),
+            indoc! {"
+                version: 1.5.0
+                appVersion: v1.5.0
+            "},
+        )?;
+
+        let rules = vec![
+            VersionRule {
+                files: "chart/*.yaml".to_string(),
+                pattern: Some(r"(^version: )(\S+)".to_string()),
+                value: "{version_no_v}".to_string(),
+                ..Default::default()
+            },
+            VersionRule {
+                files: "chart/*.yaml".to_string(),
+                path: Some("appVersion".to_string()),
+                value: "{version}".to_string(),
+                ..Default::default()
+            },
+            VersionRule {
+                files: "deploy/*.txt".to_string(),
+                pattern: Some(r"(image: )(\S+)".to_string()),
+                value: "{version}".to_string(),
+                optional: true,
+                ..Default::default()
+            },
+        ];
+        apply_version_rules(dir.path(), &rules, "v1.6.0")?;
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("chart/Chart.yaml"))?,
+            indoc! {"
+                version: 1.6.0
+                appVersion: v1.6.0
+            "}
+        );
+
+        let rules = vec![VersionRule {
+            optional: false,
+            ..rules[2].clone()
+        }];
+        assert!(apply_version_rules(dir.path(), &rules, "v1.6.0").is_err());
+
+        let rules = vec![VersionRule {
+            files: "chart/Chart.yaml".to_string(),
+            path: Some("appVersion".to_string()),
+            value: "{version}".to_string(),
+            expected_matches: Some(2),
+            ..Default::default()
+        }];
+        let updates = plan_version_rules(dir.path(), &rules, "v1.7.0")?;
+        assert_eq!(updates.matches[0].old_values, vec!["v1.6.0"]);
+        assert_eq!(updates.mismatches.len(), 1);
+
+        assert!(apply_version_rules(dir.path(), &rules, "v1.7.0").is_err());
+        assert!(
+            fs::read_to_string(dir.path().join("chart/Chart.yaml"))?.contains("appVersion: v1.6.0")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_longhorn_image_list() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("deploy"))?;
+        fs::write(
+            dir.path().join("deploy/longhorn-images.txt"),
+            indoc! {"
+                longhornio/longhorn-manager:master-head
+                longhornio/longhorn-engine:master-head
+            "},
+        )?;
+
+        let image_list_rules = |components: &[&str]| -> Vec<VersionRule> {
+            let components: Vec<String> = components.iter().map(|it| it.to_string()).collect();
+            longhorn_version_rules(&components)
+                .into_iter()
+                .filter(|it| it.files == "deploy/longhorn-images.txt")
+                .collect()
+        };
+
+        let rules = image_list_rules(&["longhorn-manager", "longhorn-engine"]);
+        let updates = plan_version_rules(dir.path(), &rules, "v1.6.0")?;
+        assert_eq!(updates.matches.len(), 2);
+        assert!(updates.mismatches.is_empty());
+
+        let rules = image_list_rules(&["longhorn-manager", "longhorn-ui"]);
+        let updates = plan_version_rules(dir.path(), &rules, "v1.6.0")?;
+        assert_eq!(updates.mismatches.len(), 1);
+        assert!(updates.mismatches[0].contains("longhorn-ui"));
+        assert!(apply_version_rules(dir.path(), &rules, "v1.6.0").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disallowed_files() -> anyhow::Result<()> {
+        let files: Vec<ChangedFile> = ["chart/Chart.yaml", "deploy/longhorn.yaml", "hook.log"]
+            .into_iter()
+            .map(|path| ChangedFile {
+                status: "M".to_string(),
+                path: path.to_string(),
+            })
+            .collect();
+
+        assert!(disallowed_files(&files, &[])?.is_empty());
+
+        let allowed_paths = vec!["chart/**".to_string(), "deploy/*.yaml".to_string()];
+        let disallowed = disallowed_files(&files, &allowed_paths)?;
+        assert_eq!(disallowed.len(), 1);
+        assert_eq!(disallowed[0].path, "hook.log");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_pr_body() {
+        let files = vec![ChangedFile {
+            status: "M".to_string(),
+            path: "chart/values.yaml".to_string(),
+        }];
+        let bumps = vec![
+            RuleMatch {
+                file: "chart/values.yaml".to_string(),
+                matcher: "image.longhorn.manager.tag".to_string(),
+                old_values: vec!["master-head".to_string()],
+                new_value: "v1.6.1".to_string(),
+            },
+            RuleMatch {
+                file: "chart/Chart.yaml".to_string(),
+                matcher: r"(version: )(\S+)".to_string(),
+                old_values: vec!["1.6.1".to_string()],
+                new_value: "1.6.1".to_string(),
+            },
+        ];
+        let pr_config = PullRequestConfig::default();
+        assert_eq!(
+            changelog_url(&pr_config, "longhorn", "charts", "v1.6.1"),
+            "https://github.com/longhorn/longhorn/releases/tag/v1.6.1"
+        );
+
+        assert_eq!(
+            render_pr_body(
+                "v1.6.1",
+                &changelog_url(&pr_config, "longhorn", "longhorn", "v1.6.1"),
+                &files,
+                &bumps
+            ),
+            indoc! {"
+                ## Release v1.6.1
+
+                Changelog: https://github.com/longhorn/longhorn/releases/tag/v1.6.1
+
+                ### Files Changed
+                - `chart/values.yaml`
+
+                ### Version Bumps
+                | File | Old | New |
+                |---|---|---|
+                | chart/values.yaml | master-head | v1.6.1 |
+            "}
+        );
+    }
+
+    #[test]
+    fn test_pull_request_options() {
+        let config: Config = serde_yaml::from_str(indoc! {"
+            pullRequests:
+              longhorn:
+                labels: [release, backport/1.6]
+                reviewers: [dev1]
+                teamReviewers: [maintainers, org/qa]
+                milestone: '{version}'
+        "})
+        .unwrap();
+
+        let options = pull_request_options(&config, "longhorn", "longhorn", "v1.6.1", None);
+        assert_eq!(options.labels, vec!["release", "backport/1.6"]);
+        assert_eq!(
+            options.reviewers,
+            vec!["dev1", "longhorn/maintainers", "org/qa"]
+        );
+        assert_eq!(options.milestone.as_deref(), Some("v1.6.1"));
+
+        let options = pull_request_options(&config, "longhorn", "charts", "v1.6.1", None);
+        assert!(options.labels.is_empty() && options.milestone.is_none());
+    }
+
     #[test]
     fn test_get_container_component_names() {
         let data = vec![
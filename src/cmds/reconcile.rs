@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::cmds::changelog::{
+    generate_repo_report, ChangelogCommit, RepoChangelog, RepoReportOptions,
+};
+use crate::cmds::release::search_issues;
+use crate::cmds::{run_repo_tasks, CliCommand};
+use crate::issue::parse_issue_refs;
+use crate::Cli;
+
+#[derive(Args)]
+#[command(about = "Reconcile milestone issues with the commits of repos between tags")]
+pub struct ReconcileArgs {
+    #[arg(long, help = "GitHub owner")]
+    owner: String,
+
+    #[arg(
+        long,
+        default_value = "longhorn",
+        help = "GitHub repo of the milestone"
+    )]
+    repo: String,
+
+    #[arg(long, help = "Milestone")]
+    milestone: String,
+
+    #[arg(long, help = "GitHub repos to search commits")]
+    repos: Vec<String>,
+
+    #[arg(long, help = "Branch")]
+    branch: String,
+
+    #[arg(long, help = "Tag")]
+    tag: Option<String>,
+
+    #[arg(long, help = "Previous tag")]
+    prev_tag: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "14",
+        help = "Search commits since days if the previous tag has no date"
+    )]
+    since_days: i64,
+
+    #[arg(
+        long,
+        help = "Create logs from the last public release, not pre release"
+    )]
+    public: bool,
+
+    #[arg(long, help = "Labels to exclude issues")]
+    exclude_labels: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        default_values = ["renovate[bot]", "dependabot[bot]"],
+        help = "Bot authors whose commits are not required to reference issues"
+    )]
+    bots: Vec<String>,
+}
+
+#[async_trait]
+impl CliCommand for ReconcileArgs {
+    async fn run(&self, _cli: &Cli) -> anyhow::Result<()> {
+        let (_, issues) = search_issues(
+            &self.owner,
+            &self.repo,
+            &self.milestone,
+            &[],
+            &self.exclude_labels.clone().unwrap_or_default(),
+            None,
+        )
+        .await?;
+        let issues: Vec<MilestoneIssue> = issues
+            .into_iter()
+            .filter(|it| it.pull_request.is_none())
+            .map(|it| MilestoneIssue {
+                number: it.number,
+                title: it.title,
+                html_url: it.html_url.to_string(),
+            })
+            .collect();
+
+        let opts = RepoReportOptions::new(
+            self.branch.clone(),
+            self.tag.clone(),
+            self.prev_tag.clone(),
+            self.since_days,
+            self.public,
+            self.bots.clone(),
+        );
+
+        let changelogs = run_repo_tasks(&self.owner, &self.repos, |owner, repo| {
+            generate_repo_report(owner, repo, opts.clone())
+        })
+        .await?
+        .into_iter()
+        .collect::<anyhow::Result<Vec<RepoChangelog>>>()?;
+
+        println!("{}", self.render_reconciliation(&issues, &changelogs));
+
+        Ok(())
+    }
+}
+
+/// An issue of the milestone, excluding pull requests.
+struct MilestoneIssue {
+    number: u64,
+    title: String,
+    html_url: String,
+}
+
+impl ReconcileArgs {
+    fn render_reconciliation(
+        &self,
+        issues: &[MilestoneIssue],
+        changelogs: &[RepoChangelog],
+    ) -> String {
+        let milestone_issues: HashSet<u64> = issues.iter().map(|it| it.number).collect();
+
+        let references_milestone = |repo_ref: &str, commit: &ChangelogCommit| {
+            let (owner, repo) = repo_ref.split_once('/').unwrap_or_default();
+
+            parse_issue_refs(&commit.message, owner, repo)
+                .into_iter()
+                .filter(|it| it.owner == self.owner && it.repo == self.repo)
+                .filter(|it| milestone_issues.contains(&it.number))
+                .map(|it| it.number)
+                .collect::<Vec<_>>()
+        };
+
+        let mut referenced_issues = HashSet::new();
+        let mut unreferenced_commits = String::new();
+
+        for changelog in changelogs {
+            let mut commits = String::new();
+
+            // Bot commits are summarized as dependency updates, and not required to reference issues
+            for commit in &changelog.commits {
+                let numbers = references_milestone(&changelog.repo_ref, commit);
+
+                if numbers.is_empty() {
+                    commits += &format!(
+                        "- {} [{}]({}) by {}\n",
+                        commit.subject(),
+                        commit.short_sha(),
+                        commit.html_url,
+                        commit.author()
+                    );
+                }
+
+                referenced_issues.extend(numbers);
+            }
+
+            if !commits.is_empty() {
+                unreferenced_commits += &format!("\n### {}\n{}", changelog.repo_ref, commits);
+            }
+        }
+
+        let mut str = format!("## Milestone {} Issues Without Commits\n", self.milestone);
+        for issue in issues {
+            if !referenced_issues.contains(&issue.number) {
+                str += &format!("- {} [{}]({})\n", issue.title, issue.number, issue.html_url);
+            }
+        }
+
+        str += &format!(
+            "\n## Commits Without Milestone {} Issues\n{}",
+            self.milestone, unreferenced_commits
+        );
+
+        str
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmds::changelog::DependencyUpdate;
+
+    use super::*;
+
+    #[test]
+    fn test_render_reconciliation() {
+        let args = ReconcileArgs {
+            owner: "longhorn".to_string(),
+            repo: "longhorn".to_string(),
+            milestone: "v1.6.1".to_string(),
+            repos: vec![],
+            branch: "v1.6.x".to_string(),
+            tag: None,
+            prev_tag: None,
+            since_days: 14,
+            public: false,
+            exclude_labels: None,
+            bots: vec!["renovate[bot]".to_string()],
+        };
+
+        let issues: Vec<MilestoneIssue> =
+            [(100, "Fix backup"), (101, "Improve upgrade"), (102, "Doc")]
+                .into_iter()
+                .map(|(number, title)| MilestoneIssue {
+                    number,
+                    title: title.to_string(),
+                    html_url: format!("https://github.com/longhorn/longhorn/issues/{number}"),
+                })
+                .collect();
+
+        // (sha, message, author, listed as a commit without milestone issues)
+        let commits = [
+            (
+                "aaaaaaaa",
+                "fix: backup (longhorn/longhorn#100)",
+                "alice",
+                false,
+            ),
+            (
+                "bbbbbbbb",
+                "feat: upgrade\n\nhttps://github.com/longhorn/longhorn/issues/101",
+                "bob",
+                false,
+            ),
+            (
+                "cccccccc",
+                "fix: volume (longhorn/longhorn#999)",
+                "alice",
+                true,
+            ),
+            ("dddddddd", "fix: engine (#100)", "bob", true),
+            ("eeeeeeee", "chore: cleanup", "carol", true),
+        ];
+
+        let changelogs = vec![RepoChangelog {
+            repo_ref: "longhorn/longhorn-manager".to_string(),
+            commits: commits
+                .iter()
+                .map(|(sha, message, author, _)| {
+                    ChangelogCommit::for_test(sha, message, Some(author))
+                })
+                .collect(),
+            // The bot commits are summarized as dependency updates by generate_repo_report
+            dependency_updates: vec![DependencyUpdate::parse(ChangelogCommit::for_test(
+                "99999999",
+                "chore(deps): bump golang.org/x/net from 0.17.0 to 0.18.0",
+                Some("dependabot[bot]"),
+            ))],
+            ..Default::default()
+        }];
+
+        let str = args.render_reconciliation(&issues, &changelogs);
+        let (issues_section, commits_section) = str
+            .split_once("## Commits Without Milestone v1.6.1 Issues")
+            .unwrap();
+
+        // (issue, listed as an issue without commits)
+        for (number, listed) in [(100, false), (101, false), (102, true)] {
+            assert_eq!(
+                issues_section.contains(&format!("[{number}]")),
+                listed,
+                "issue {number}"
+            );
+        }
+
+        for (sha, _, _, listed) in commits {
+            assert_eq!(commits_section.contains(sha), listed, "commit {sha}");
+        }
+        assert!(!commits_section.contains("99999999"));
+        assert!(commits_section.contains("### longhorn/longhorn-manager"));
+    }
+}
@@ -165,102 +165,117 @@ fn read_note_file(path: impl Into<PathBuf>) -> String {
     fs::read_to_string(path).unwrap_or_default()
 }
 
-impl ReleaseArgs {
-    async fn search_issues(&self) -> anyhow::Result<(HashSet<u64>, Vec<Issue>)> {
-        log::info!("Searching issues");
-
-        let labels = self.labels.clone().unwrap_or_default();
-        let exclude_labels = self.exclude_labels.clone().unwrap_or_default();
-
-        let milestones: Vec<Milestone> = github_client()
-            .get(
-                format!("/repos/{}/{}/milestones", self.owner, self.repo),
-                None::<&()>,
-            )
-            .await?;
-
-        let milestone =
-            if let Some(milestone) = milestones.iter().find(|m| m.title == self.milestone) {
-                milestone
-            } else {
-                return Err(anyhow!("{} milestone not found", self.milestone));
-            };
-
-        log::info!(
-            "Searching issues by milestone: {}, labels: {:?}",
-            milestone.title,
-            labels
-        );
-
-        let mut issues: Vec<Issue> = vec![];
-        let mut issue_ids = hashset! {};
-
-        let issue_handler = github_client().issues(&self.owner, &self.repo);
-        let since_date = Utc::now() - Duration::days(self.since_days);
-
-        for search_type in ["label", "milestone"] {
-            let mut page: u32 = 1;
-
-            loop {
-                let builder = match search_type {
-                    "label" => {
-                        if labels.is_empty() {
-                            break;
-                        }
-                        issue_handler.list().labels(&labels).state(State::All)
+/// Searches the issues of the milestone, and the issues with the labels outside the milestone.
+pub(crate) async fn search_issues(
+    owner: &str,
+    repo: &str,
+    milestone: &str,
+    labels: &[String],
+    exclude_labels: &[String],
+    since_days: Option<i64>,
+) -> anyhow::Result<(HashSet<u64>, Vec<Issue>)> {
+    log::info!("Searching issues");
+
+    let milestones: Vec<Milestone> = github_client()
+        .get(format!("/repos/{}/{}/milestones", owner, repo), None::<&()>)
+        .await?;
+
+    let milestone = if let Some(milestone) = milestones.iter().find(|m| m.title == milestone) {
+        milestone
+    } else {
+        return Err(anyhow!("{} milestone not found", milestone));
+    };
+
+    log::info!(
+        "Searching issues by milestone: {}, labels: {:?}",
+        milestone.title,
+        labels
+    );
+
+    let mut issues: Vec<Issue> = vec![];
+    let mut issue_ids = hashset! {};
+
+    let issue_handler = github_client().issues(owner, repo);
+    let since_date = since_days.map(|it| Utc::now() - Duration::days(it));
+
+    for search_type in ["label", "milestone"] {
+        let mut page: u32 = 1;
+
+        loop {
+            let builder = match search_type {
+                "label" => {
+                    if labels.is_empty() {
+                        break;
                     }
-                    "milestone" => issue_handler
-                        .list()
-                        .milestone(milestone.number as u64)
-                        .state(State::All),
-                    _ => return Err(anyhow!("invalid search type")),
+                    issue_handler.list().labels(labels).state(State::All)
                 }
-                .sort(Sort::Updated)
-                .since(since_date);
+                "milestone" => issue_handler
+                    .list()
+                    .milestone(milestone.number as u64)
+                    .state(State::All),
+                _ => return Err(anyhow!("invalid search type")),
+            }
+            .sort(Sort::Updated);
+            let builder = match since_date {
+                Some(since_date) => builder.since(since_date),
+                None => builder,
+            };
 
-                let mut results = builder.page(page).send().await?.items;
+            let mut results = builder.page(page).send().await?.items;
 
-                results.retain(|issue| {
-                    if let Some(closed_at) = issue.closed_at {
-                        if closed_at < since_date {
-                            return false;
-                        }
+            results.retain(|issue| {
+                if let (Some(closed_at), Some(since_date)) = (issue.closed_at, since_date) {
+                    if closed_at < since_date {
+                        return false;
                     }
-
-                    !issue
-                        .labels
-                        .iter()
-                        .any(|label| exclude_labels.contains(&label.name))
-                });
-
-                if results.is_empty() {
-                    break;
                 }
 
-                results.iter().for_each(|it| {
-                    issue_ids.insert(it.number);
-                });
+                !issue
+                    .labels
+                    .iter()
+                    .any(|label| exclude_labels.contains(&label.name))
+            });
 
-                issues.append(&mut results);
-                page += 1;
+            if results.is_empty() {
+                break;
             }
+
+            results.iter().for_each(|it| {
+                issue_ids.insert(it.number);
+            });
+
+            issues.append(&mut results);
+            page += 1;
         }
+    }
+
+    Ok((issue_ids, issues))
+}
 
-        Ok((issue_ids, issues))
+impl ReleaseArgs {
+    async fn search_issues(&self) -> anyhow::Result<(HashSet<u64>, Vec<Issue>)> {
+        search_issues(
+            &self.owner,
+            &self.repo,
+            &self.milestone,
+            &self.labels.clone().unwrap_or_default(),
+            &self.exclude_labels.clone().unwrap_or_default(),
+            Some(self.since_days),
+        )
+        .await
     }
 
     fn create_release(
         &self,
         issue_ids: &mut HashSet<u64>,
         issues: &Vec<Issue>,
-        pre_note: &String,
-        post_note: &String,
+        pre_note: &str,
+        post_note: &str,
         note_section_disable: bool,
     ) -> anyhow::Result<String> {
         log::info!("Creating a release for {}", self.tag);
 
-        let mut note = pre_note.clone();
-        let post_note = post_note.clone();
+        let mut note = pre_note.to_string();
         let mut sections: indexmap::IndexMap<String, Vec<&Issue>> = indexmap! {};
         let mut contributors = indexset! {};
 
@@ -337,7 +352,7 @@ impl ReleaseArgs {
             }
         }
 
-        note += post_note.as_str();
+        note += post_note;
 
         note += "\n## Contributors\n";
         contributors.sort();
@@ -407,7 +422,7 @@ mod tests {
         let dir = tempdir()?;
 
         let mut files = vec![];
-        for file in vec!["dummy.sbom", "dummy2.sbom"] {
+        for file in ["dummy.sbom", "dummy2.sbom"] {
             files.push(
                 File::create(dir.path().join(file))?
                     .path()?
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+/// The renote config file, e.g. renote.yaml.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Config {
+    /// Rules to bump versions in the files of a repo by `renote pr`.
+    pub(crate) version_rules: Vec<VersionRule>,
+    /// How to render the deploy manifest from the chart.
+    pub(crate) deploy_manifest: DeployManifest,
+    /// Settings of the PRs created by `renote pr`, by repo name.
+    pub(crate) pull_requests: HashMap<String, PullRequestConfig>,
+}
+
+impl Config {
+    /// Returns the PR settings of the repo, or the default settings if not configured.
+    pub(crate) fn pull_request(&self, repo: &str) -> PullRequestConfig {
+        self.pull_requests.get(repo).cloned().unwrap_or_default()
+    }
+
+    /// Loads the config file, or returns the default config if no file is given.
+    pub(crate) fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let content =
+            fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+
+        serde_yaml::from_str(&content).with_context(|| format!("failed to parse {:?}", path))
+    }
+}
+
+/// Settings of the PRs of a repo.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct PullRequestConfig {
+    /// Globs of the paths a PR may touch. Any path is allowed if empty.
+    pub(crate) allowed_paths: Vec<String>,
+    /// Labels of the PR, e.g. release, backport/1.6.
+    pub(crate) labels: Vec<String>,
+    pub(crate) reviewers: Vec<String>,
+    /// Teams of the owner requested to review, e.g. maintainers.
+    pub(crate) team_reviewers: Vec<String>,
+    pub(crate) assignees: Vec<String>,
+    /// Milestone of the PR, supporting {version}.
+    pub(crate) milestone: Option<String>,
+    /// Link of the changelog in the PR body, supporting {owner}, {repo} and {version}.
+    /// Defaults to the GitHub release of longhorn/longhorn.
+    pub(crate) changelog_url: Option<String>,
+}
+
+/// The deploy manifest rendered from the chart by `helm template`, e.g. deploy/longhorn.yaml.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct DeployManifest {
+    /// Chart directory relative to the repo.
+    pub(crate) chart: String,
+    /// Values files relative to the repo, applied in order.
+    pub(crate) values: Vec<String>,
+    pub(crate) release_name: String,
+    pub(crate) namespace: String,
+    /// Prepend the Namespace object, since `helm template` does not render it.
+    pub(crate) create_namespace: bool,
+    /// Output file relative to the repo.
+    pub(crate) output: String,
+}
+
+impl Default for DeployManifest {
+    fn default() -> Self {
+        Self {
+            chart: "chart".to_string(),
+            values: vec![],
+            release_name: "longhorn".to_string(),
+            namespace: "longhorn-system".to_string(),
+            create_namespace: true,
+            output: "deploy/longhorn.yaml".to_string(),
+        }
+    }
+}
+
+/// A rule to replace the version in the files matching a glob.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct VersionRule {
+    /// Glob of the files relative to the repo, e.g. chart/*.yaml.
+    pub(crate) files: String,
+    /// Regex whose match after the first capture group is replaced, e.g. `(appVersion: )(\S+)`.
+    pub(crate) pattern: Option<String>,
+    /// YAML path of the replaced value, e.g. `image.longhorn.manager.tag`. Used instead of `pattern`.
+    pub(crate) path: Option<String>,
+    /// Template of the new value, supporting {version}, {version_no_v}, {major}, {minor},
+    /// {patch} and {pre}.
+    #[serde(default = "default_value_template")]
+    pub(crate) value: String,
+    /// Skip the rule instead of failing if no file or text matches.
+    #[serde(default)]
+    pub(crate) optional: bool,
+    /// The total number of matches expected in all files, at least one if not set.
+    #[serde(default)]
+    pub(crate) expected_matches: Option<usize>,
+}
+
+fn default_value_template() -> String {
+    "{version}".to_string()
+}
+
+impl VersionRule {
+    /// Returns the pattern or YAML path of the rule.
+    pub(crate) fn matcher(&self) -> anyhow::Result<&str> {
+        match (&self.pattern, &self.path) {
+            (Some(pattern), None) => Ok(pattern),
+            (None, Some(path)) => Ok(path),
+            _ => Err(anyhow!(
+                "version rule of {} requires either a pattern or a path",
+                self.files
+            )),
+        }
+    }
+
+    /// Returns whether the total number of matches is expected by the rule.
+    pub(crate) fn is_expected(&self, matches: usize) -> bool {
+        if matches == 0 && self.optional {
+            return true;
+        }
+
+        match self.expected_matches {
+            Some(expected) => matches == expected,
+            None => matches > 0,
+        }
+    }
+
+    /// Renders the value template with the version (e.g. v1.6.0-rc1).
+    pub(crate) fn render_value(&self, version: &str) -> anyhow::Result<String> {
+        let version_no_v = version.trim_start_matches('v');
+        let semver = semver::Version::parse(version_no_v);
+
+        let mut value = self
+            .value
+            .replace("{version}", version)
+            .replace("{version_no_v}", version_no_v);
+
+        for (placeholder, part) in [
+            ("{major}", semver.as_ref().map(|it| it.major.to_string())),
+            ("{minor}", semver.as_ref().map(|it| it.minor.to_string())),
+            ("{patch}", semver.as_ref().map(|it| it.patch.to_string())),
+            ("{pre}", semver.as_ref().map(|it| it.pre.to_string())),
+        ] {
+            if value.contains(placeholder) {
+                let part = part.map_err(|err| {
+                    anyhow!(
+                        "{} requires a semver version, {}: {}",
+                        placeholder,
+                        version,
+                        err
+                    )
+                })?;
+                value = value.replace(placeholder, &part);
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_value() -> anyhow::Result<()> {
+        let data = vec![
+            ("{version}", "v1.6.0-rc1", "v1.6.0-rc1"),
+            ("{version_no_v}", "v1.6.0-rc1", "1.6.0-rc1"),
+            ("v{major}.{minor}.x", "v1.6.0-rc1", "v1.6.x"),
+            ("{patch}-{pre}", "v1.6.2-rc1", "2-rc1"),
+        ];
+
+        for (template, version, expected) in data {
+            let rule = VersionRule {
+                value: template.to_string(),
+                ..Default::default()
+            };
+
+            assert_eq!(rule.render_value(version)?, expected);
+        }
+
+        let rule = VersionRule {
+            value: "{major}".to_string(),
+            ..Default::default()
+        };
+        assert!(rule.render_value("master-head").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+/// A CustomResourceDefinition with the schema fields of each version.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Crd {
+    pub(crate) name: String,
+    pub(crate) storage_version: Option<String>,
+    /// Field paths and types by version (e.g. v1beta2 => spec.size => string).
+    pub(crate) versions: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// A schema field added, removed or retyped in a CRD version.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct FieldChange {
+    pub(crate) version: String,
+    pub(crate) field: String,
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+}
+
+/// Changes of a CRD existing in both manifests.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct CrdChange {
+    pub(crate) name: String,
+    pub(crate) added_versions: Vec<String>,
+    pub(crate) removed_versions: Vec<String>,
+    pub(crate) prev_storage_version: Option<String>,
+    pub(crate) storage_version: Option<String>,
+    pub(crate) fields: Vec<FieldChange>,
+}
+
+impl CrdChange {
+    pub(crate) fn is_storage_version_changed(&self) -> bool {
+        self.prev_storage_version != self.storage_version
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added_versions.is_empty()
+            && self.removed_versions.is_empty()
+            && !self.is_storage_version_changed()
+            && self.fields.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct CrdDiff {
+    pub(crate) added_crds: Vec<String>,
+    pub(crate) removed_crds: Vec<String>,
+    pub(crate) changed_crds: Vec<CrdChange>,
+}
+
+/// Parses the CustomResourceDefinitions of a multi-document manifest like deploy/longhorn.yaml.
+pub(crate) fn parse_crds(content: &str) -> anyhow::Result<BTreeMap<String, Crd>> {
+    let mut crds = BTreeMap::new();
+
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = Value::deserialize(document)?;
+
+        if value["kind"].as_str() != Some("CustomResourceDefinition") {
+            continue;
+        }
+
+        let name = value["metadata"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let mut crd = Crd {
+            name: name.clone(),
+            ..Default::default()
+        };
+
+        for version in value["spec"]["versions"]
+            .as_sequence()
+            .into_iter()
+            .flatten()
+        {
+            let version_name = version["name"].as_str().unwrap_or_default().to_string();
+
+            if version["storage"].as_bool() == Some(true) {
+                crd.storage_version = Some(version_name.clone());
+            }
+
+            let mut fields = BTreeMap::new();
+            collect_schema_fields("", &version["schema"]["openAPIV3Schema"], &mut fields);
+            crd.versions.insert(version_name, fields);
+        }
+
+        crds.insert(name, crd);
+    }
+
+    Ok(crds)
+}
+
+fn schema_type(schema: &Value) -> String {
+    if schema["x-kubernetes-int-or-string"].as_bool() == Some(true) {
+        return "int-or-string".to_string();
+    }
+
+    match (schema["type"].as_str(), schema["format"].as_str()) {
+        (Some(t), Some(format)) => format!("{t}({format})"),
+        (Some(t), None) => t.to_string(),
+        (None, _) if schema["properties"].is_mapping() => "object".to_string(),
+        _ => "any".to_string(),
+    }
+}
+
+fn collect_schema_fields(prefix: &str, schema: &Value, fields: &mut BTreeMap<String, String>) {
+    if let Some(properties) = schema["properties"].as_mapping() {
+        for (key, property) in properties {
+            let key = key.as_str().unwrap_or_default();
+            let path = if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{prefix}.{key}")
+            };
+
+            fields.insert(path.clone(), schema_type(property));
+            collect_schema_fields(&path, property, fields);
+        }
+    }
+
+    if schema["items"].is_mapping() {
+        let path = format!("{prefix}[]");
+        fields.insert(path.clone(), schema_type(&schema["items"]));
+        collect_schema_fields(&path, &schema["items"], fields);
+    }
+
+    if schema["additionalProperties"].is_mapping() {
+        let path = format!("{prefix}.*");
+        fields.insert(path.clone(), schema_type(&schema["additionalProperties"]));
+        collect_schema_fields(&path, &schema["additionalProperties"], fields);
+    }
+}
+
+pub(crate) fn diff_crds(
+    prev_crds: &BTreeMap<String, Crd>,
+    crds: &BTreeMap<String, Crd>,
+) -> CrdDiff {
+    let mut diff = CrdDiff {
+        added_crds: crds
+            .keys()
+            .filter(|it| !prev_crds.contains_key(*it))
+            .cloned()
+            .collect(),
+        removed_crds: prev_crds
+            .keys()
+            .filter(|it| !crds.contains_key(*it))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+
+    for (name, crd) in crds {
+        let Some(prev_crd) = prev_crds.get(name) else {
+            continue;
+        };
+
+        let mut change = CrdChange {
+            name: name.clone(),
+            added_versions: crd
+                .versions
+                .keys()
+                .filter(|it| !prev_crd.versions.contains_key(*it))
+                .cloned()
+                .collect(),
+            removed_versions: prev_crd
+                .versions
+                .keys()
+                .filter(|it| !crd.versions.contains_key(*it))
+                .cloned()
+                .collect(),
+            prev_storage_version: prev_crd.storage_version.clone(),
+            storage_version: crd.storage_version.clone(),
+            fields: vec![],
+        };
+
+        for (version, fields) in &crd.versions {
+            let Some(prev_fields) = prev_crd.versions.get(version) else {
+                continue;
+            };
+
+            for (field, field_type) in fields {
+                match prev_fields.get(field) {
+                    Some(prev_type) if prev_type == field_type => {}
+                    prev_type => change.fields.push(FieldChange {
+                        version: version.clone(),
+                        field: field.clone(),
+                        from: prev_type.cloned(),
+                        to: Some(field_type.clone()),
+                    }),
+                }
+            }
+
+            for (field, prev_type) in prev_fields {
+                if !fields.contains_key(field) {
+                    change.fields.push(FieldChange {
+                        version: version.clone(),
+                        field: field.clone(),
+                        from: Some(prev_type.clone()),
+                        to: None,
+                    });
+                }
+            }
+
+            change
+                .fields
+                .sort_by(|a, b| (&a.version, &a.field).cmp(&(&b.version, &b.field)));
+        }
+
+        if !change.is_empty() {
+            diff.changed_crds.push(change);
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_crds() -> anyhow::Result<()> {
+        let prev_content = indoc! {"
+            apiVersion: v1
+            kind: ServiceAccount
+            metadata:
+              name: longhorn-service-account
+            ---
+            apiVersion: apiextensions.k8s.io/v1
+            kind: CustomResourceDefinition
+            metadata:
+              name: volumes.longhorn.io
+            spec:
+              versions:
+              - name: v1beta1
+                served: true
+                storage: true
+                schema:
+                  openAPIV3Schema:
+                    type: object
+                    properties:
+                      spec:
+                        type: object
+                        properties:
+                          size:
+                            type: string
+                          replicas:
+                            type: integer
+            ---
+            apiVersion: apiextensions.k8s.io/v1
+            kind: CustomResourceDefinition
+            metadata:
+              name: engines.longhorn.io
+            spec:
+              versions: []
+        "};
+        let content = indoc! {"
+            apiVersion: apiextensions.k8s.io/v1
+            kind: CustomResourceDefinition
+            metadata:
+              name: volumes.longhorn.io
+            spec:
+              versions:
+              - name: v1beta1
+                served: true
+                storage: false
+                schema:
+                  openAPIV3Schema:
+                    type: object
+                    properties:
+                      spec:
+                        type: object
+                        properties:
+                          size:
+                            x-kubernetes-int-or-string: true
+                          nodeSelector:
+                            type: array
+                            items:
+                              type: string
+              - name: v1beta2
+                served: true
+                storage: true
+            ---
+            apiVersion: apiextensions.k8s.io/v1
+            kind: CustomResourceDefinition
+            metadata:
+              name: orphans.longhorn.io
+            spec:
+              versions: []
+        "};
+
+        let diff = diff_crds(&parse_crds(prev_content)?, &parse_crds(content)?);
+
+        assert_eq!(diff.added_crds, vec!["orphans.longhorn.io"]);
+        assert_eq!(diff.removed_crds, vec!["engines.longhorn.io"]);
+        assert_eq!(diff.changed_crds.len(), 1);
+
+        let change = &diff.changed_crds[0];
+        assert_eq!(change.added_versions, vec!["v1beta2"]);
+        assert!(change.is_storage_version_changed());
+
+        let fields: Vec<(&str, Option<&str>, Option<&str>)> = change
+            .fields
+            .iter()
+            .map(|it| (it.field.as_str(), it.from.as_deref(), it.to.as_deref()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("spec.nodeSelector", None, Some("array")),
+                ("spec.nodeSelector[]", None, Some("string")),
+                ("spec.replicas", Some("integer"), None),
+                ("spec.size", Some("string"), Some("int-or-string")),
+            ]
+        );
+
+        Ok(())
+    }
+}
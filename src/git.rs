@@ -1,7 +1,8 @@
 use std::fs;
 use std::fs::File;
 use std::io::{BufRead, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::OnceLock;
 
 use anyhow::anyhow;
@@ -24,6 +25,45 @@ pub trait GitOperationTrait {
 
     fn tag_hash(&self, tag: &str, branch: &str) -> anyhow::Result<String>;
     fn previous_tag(&self, tag: &str, is_public: bool) -> anyhow::Result<String>;
+
+    fn rev_list(
+        &self,
+        range: &str,
+        first_parent: bool,
+        pathspecs: &[String],
+    ) -> anyhow::Result<Vec<String>>;
+
+    fn show_file(&self, rev: &str, path: &str) -> anyhow::Result<String>;
+
+    fn latest_tag(&self, rev: &str) -> anyhow::Result<String>;
+
+    fn log(&self, range: &str, format: &str) -> anyhow::Result<Vec<String>>;
+
+    fn commits(&self, range: &str) -> anyhow::Result<Vec<GitCommit>>;
+
+    fn list_files(&self, rev: &str) -> anyhow::Result<Vec<String>>;
+
+    /// Returns the changed files of the working tree, including untracked files.
+    fn changed_files(&self) -> anyhow::Result<Vec<ChangedFile>>;
+
+    /// Returns the unified diff of the working tree against HEAD, including untracked files.
+    fn diff(&self) -> anyhow::Result<String>;
+}
+
+/// A changed file of the working tree, with the two-letter status of `git status --porcelain`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChangedFile {
+    pub status: String,
+    pub path: String,
+}
+
+/// A non-merge commit read from the local repo.
+#[derive(Clone, Debug, Default)]
+pub struct GitCommit {
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub message: String,
 }
 
 pub(crate) struct GitRepo {
@@ -227,4 +267,272 @@ impl GitOperationTrait for GitCli {
             Err(anyhow!("previous tag not found"))
         }
     }
+
+    fn rev_list(
+        &self,
+        range: &str,
+        first_parent: bool,
+        pathspecs: &[String],
+    ) -> anyhow::Result<Vec<String>> {
+        let mut args = vec!["rev-list"];
+        if first_parent {
+            args.push("--first-parent");
+        }
+        args.push(range);
+        if !pathspecs.is_empty() {
+            args.push("--");
+            args.extend(pathspecs.iter().map(|it| it.as_str()));
+        }
+
+        let output = cmd!("git", &self.repo.repo_dir_path(), &args);
+
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(|it| it.to_string())
+            .collect())
+    }
+
+    fn show_file(&self, rev: &str, path: &str) -> anyhow::Result<String> {
+        let output = cmd!(
+            "git",
+            &self.repo.repo_dir_path(),
+            ["show", &format!("{rev}:{path}")]
+        );
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn latest_tag(&self, rev: &str) -> anyhow::Result<String> {
+        let output = cmd!(
+            "git",
+            &self.repo.repo_dir_path(),
+            ["describe", "--tags", "--abbrev=0", rev]
+        );
+
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    }
+
+    fn log(&self, range: &str, format: &str) -> anyhow::Result<Vec<String>> {
+        let output = cmd!(
+            "git",
+            &self.repo.repo_dir_path(),
+            ["log", &format!("--format={format}"), range]
+        );
+
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(|it| it.to_string())
+            .collect())
+    }
+
+    fn commits(&self, range: &str) -> anyhow::Result<Vec<GitCommit>> {
+        let output = cmd!(
+            "git",
+            &self.repo.repo_dir_path(),
+            [
+                "log",
+                "--no-merges",
+                "--format=%H%x1f%an%x1f%ae%x1f%B%x1e",
+                range
+            ]
+        );
+
+        Ok(String::from_utf8(output.stdout)?
+            .split('\x1e')
+            .filter_map(|record| {
+                let mut fields = record.trim_start_matches('\n').splitn(4, '\x1f');
+
+                Some(GitCommit {
+                    sha: fields.next().filter(|it| !it.is_empty())?.to_string(),
+                    author_name: fields.next()?.to_string(),
+                    author_email: fields.next()?.to_string(),
+                    message: fields.next()?.trim_end().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    fn list_files(&self, rev: &str) -> anyhow::Result<Vec<String>> {
+        let output = cmd!(
+            "git",
+            &self.repo.repo_dir_path(),
+            ["ls-tree", "-r", "--name-only", rev]
+        );
+
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .map(|it| it.to_string())
+            .collect())
+    }
+
+    fn changed_files(&self) -> anyhow::Result<Vec<ChangedFile>> {
+        let output = cmd!(
+            "git",
+            &self.repo.repo_dir_path(),
+            ["status", "--porcelain", "--untracked-files=all", "-z"]
+        );
+
+        Ok(parse_porcelain_status(&String::from_utf8(output.stdout)?))
+    }
+
+    fn diff(&self) -> anyhow::Result<String> {
+        let repo_dir_path = self.repo.repo_dir_path();
+        let output = cmd!("git", repo_dir_path, ["diff", "--no-color", "HEAD"]);
+        let mut diff = String::from_utf8(output.stdout)?;
+
+        for file in self.changed_files()? {
+            if file.status == "??" {
+                diff += &diff_untracked_file(repo_dir_path, &file.path)?;
+            }
+        }
+
+        Ok(diff)
+    }
+}
+
+/// Returns the diff of an untracked file as a new file, without adding it to the index.
+pub(crate) fn diff_untracked_file(repo_dir_path: &Path, path: &str) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .current_dir(repo_dir_path)
+        .args(["diff", "--no-color", "--no-index", "--", "/dev/null", path])
+        .output()?;
+
+    // Exits with 1 if there are differences
+    match output.status.code() {
+        Some(0) | Some(1) => Ok(String::from_utf8(output.stdout)?),
+        _ => Err(anyhow!(
+            "failed to diff {}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// Returns the content of the file at the rev, or an empty string if the file does not exist
+/// at the rev. Fails if the rev is not a commit of the repo.
+pub(crate) fn show_file_or_empty(
+    repo_dir_path: &Path,
+    rev: &str,
+    path: &str,
+) -> anyhow::Result<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(repo_dir_path)
+            .args(args)
+            .output()
+    };
+
+    let output = git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{rev}^{{commit}}"),
+    ])?;
+    if !output.status.success() {
+        return Err(anyhow!("revision {} not found", rev));
+    }
+    let commit = String::from_utf8(output.stdout)?.trim().to_string();
+
+    let object = format!("{commit}:{path}");
+    if !git(&["cat-file", "-e", &object])?.status.success() {
+        log::info!("{} does not exist at {}, treated as empty", path, rev);
+        return Ok(String::new());
+    }
+
+    let output = cmd!("git", repo_dir_path, ["show", object.as_str()]);
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Parses the output of `git status --porcelain -z`, where the original path of a rename
+/// follows as a separate entry.
+fn parse_porcelain_status(output: &str) -> Vec<ChangedFile> {
+    let mut files = vec![];
+    let mut entries = output.split('\0').filter(|it| !it.is_empty());
+
+    while let Some(entry) = entries.next() {
+        let Some((status, path)) = entry.split_at_checked(2) else {
+            continue;
+        };
+        if status.contains(['R', 'C']) {
+            entries.next();
+        }
+
+        files.push(ChangedFile {
+            status: status.trim().to_string(),
+            path: path.trim_start().to_string(),
+        });
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_untracked_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        cmd!("git", dir.path(), ["init", "-q"]);
+        fs::write(dir.path().join("hook.log"), "done\n")?;
+
+        let diff = diff_untracked_file(dir.path(), "hook.log")?;
+        assert!(diff.contains("new file mode"));
+        assert!(diff.contains("+done"));
+
+        let status = cmd!("git", dir.path(), ["status", "--porcelain"]);
+        assert_eq!(String::from_utf8(status.stdout)?, "?? hook.log\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_file_or_empty() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for args in [
+            vec!["init", "-q"],
+            vec!["config", "user.name", "renote"],
+            vec!["config", "user.email", "renote@example.com"],
+        ] {
+            cmd!("git", dir.path(), &args);
+        }
+        fs::write(
+            dir.path().join("images.txt"),
+            "longhornio/longhorn-manager:v1.6.0\n",
+        )?;
+        cmd!("git", dir.path(), ["add", "."]);
+        cmd!("git", dir.path(), ["commit", "-q", "-m", "init"]);
+        cmd!("git", dir.path(), ["tag", "v1.6.0"]);
+
+        assert_eq!(
+            show_file_or_empty(dir.path(), "v1.6.0", "images.txt")?,
+            "longhornio/longhorn-manager:v1.6.0\n"
+        );
+        assert_eq!(show_file_or_empty(dir.path(), "v1.6.0", "missing.txt")?, "");
+        assert!(show_file_or_empty(dir.path(), "v1.6.1", "images.txt").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_porcelain_status() {
+        let output = " M chart/Chart.yaml\0?? hook.log\0R  deploy/new.yaml\0deploy/old.yaml\0A  chart/README.md\0";
+
+        let files = parse_porcelain_status(output);
+        let files: Vec<(&str, &str)> = files
+            .iter()
+            .map(|it| (it.status.as_str(), it.path.as_str()))
+            .collect();
+
+        assert_eq!(
+            files,
+            vec![
+                ("M", "chart/Chart.yaml"),
+                ("??", "hook.log"),
+                ("R", "deploy/new.yaml"),
+                ("A", "chart/README.md"),
+            ]
+        );
+    }
 }
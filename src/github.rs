@@ -1,7 +1,10 @@
 use anyhow::anyhow;
 use async_trait::async_trait;
+use clap::ValueEnum;
+use octocrab::models::commits::{Commit, CommitComparison};
 use octocrab::models::repos::Tag;
 use octocrab::Octocrab;
+use serde::Deserialize;
 use tracing_log::log;
 
 use crate::cmd;
@@ -11,11 +14,248 @@ pub fn github_client<'a>() -> &'a Octocrab {
     crate::global::GITHUB_CLIENT.get().unwrap()
 }
 
+/// Lists the commits of `base...head` from oldest to newest, following the pagination.
+pub(crate) async fn compare_commits(
+    owner: &str,
+    repo: &str,
+    base: &str,
+    head: &str,
+) -> anyhow::Result<Vec<Commit>> {
+    let mut commits = vec![];
+    let mut page = 1;
+
+    loop {
+        let comparison: CommitComparison = github_client()
+            .get(
+                format!("/repos/{}/{}/compare/{}...{}", owner, repo, base, head),
+                Some(&[("per_page", "100"), ("page", &page.to_string())]),
+            )
+            .await?;
+        page += 1;
+
+        if comparison.commits.is_empty() {
+            break;
+        }
+        commits.extend(comparison.commits);
+
+        if commits.len() as i64 >= comparison.total_commits {
+            break;
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Returns whether `rev` is reachable from `head`.
+pub(crate) async fn is_ancestor(
+    owner: &str,
+    repo: &str,
+    rev: &str,
+    head: &str,
+) -> anyhow::Result<bool> {
+    let comparison: serde_json::Value = github_client()
+        .get(
+            format!("/repos/{}/{}/compare/{}...{}", owner, repo, rev, head),
+            Some(&[("per_page", "1")]),
+        )
+        .await?;
+
+    Ok(matches!(
+        comparison["status"].as_str(),
+        Some("ahead") | Some("identical")
+    ))
+}
+
+/// Lists the tag names of the repo.
+pub(crate) async fn list_tags(owner: &str, repo: &str) -> anyhow::Result<Vec<String>> {
+    let mut tags = vec![];
+    let mut page = 1;
+
+    loop {
+        let result: Vec<Tag> = github_client()
+            .get(
+                format!("/repos/{}/{}/tags", owner, repo),
+                Some(&[("per_page", "100"), ("page", &page.to_string())]),
+            )
+            .await?;
+        page += 1;
+
+        if result.is_empty() {
+            break;
+        }
+        tags.extend(result.into_iter().map(|it| it.name));
+    }
+
+    Ok(tags)
+}
+
+/// Reads the file of the repo at the rev.
+pub(crate) async fn get_file(
+    owner: &str,
+    repo: &str,
+    rev: &str,
+    path: &str,
+) -> anyhow::Result<String> {
+    let mut content = github_client()
+        .repos(owner, repo)
+        .get_content()
+        .path(path)
+        .r#ref(rev)
+        .send()
+        .await?;
+
+    content
+        .take_items()
+        .into_iter()
+        .next()
+        .and_then(|it| it.decoded_content())
+        .ok_or(anyhow!("{} not found in {}/{}@{}", path, owner, repo, rev))
+}
+
+/// The body and metadata of a created PR. The body is filled from the commit if not set.
+#[derive(Clone, Debug, Default)]
+pub struct PullRequestOptions {
+    pub body: Option<String>,
+    pub labels: Vec<String>,
+    /// Users or teams like `longhorn/maintainers`.
+    pub reviewers: Vec<String>,
+    pub assignees: Vec<String>,
+    pub milestone: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum MergeMethod {
+    Merge,
+    Squash,
+    #[default]
+    Rebase,
+}
+
+/// The state, checks and reviews of a PR read by `gh pr view --json`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestStatus {
+    pub state: String,
+    /// APPROVED, CHANGES_REQUESTED, REVIEW_REQUIRED, or empty if reviews are not required.
+    #[serde(default)]
+    pub review_decision: Option<String>,
+    #[serde(default)]
+    pub status_check_rollup: Vec<CheckStatus>,
+    #[serde(default)]
+    pub merge_commit: Option<MergeCommit>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MergeCommit {
+    pub oid: String,
+}
+
+/// Either a check run with a status and conclusion, or a commit status with a state.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckStatus {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub context: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub conclusion: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MergeReadiness {
+    Ready,
+    /// Waiting for the checks or reviews.
+    Pending(Vec<String>),
+    Failed(Vec<String>),
+}
+
+impl CheckStatus {
+    fn display_name(&self) -> &str {
+        match self.name.is_empty() {
+            true => &self.context,
+            false => &self.name,
+        }
+    }
+
+    /// Returns whether the check has passed, or None if it is still pending.
+    fn passed(&self) -> Option<bool> {
+        if let Some(state) = self.state.as_deref() {
+            return match state {
+                "PENDING" | "EXPECTED" => None,
+                state => Some(state == "SUCCESS"),
+            };
+        }
+
+        if self.status.as_deref() != Some("COMPLETED") {
+            return None;
+        }
+        Some(matches!(
+            self.conclusion.as_deref(),
+            Some("SUCCESS" | "NEUTRAL" | "SKIPPED")
+        ))
+    }
+}
+
+impl PullRequestStatus {
+    /// Returns whether the PR can be merged, waiting for the approval as well if reviews are
+    /// required. Waits for checks to be reported if `expect_checks`, since CI registers them
+    /// some time after the PR is created.
+    pub fn readiness(&self, require_review: bool, expect_checks: bool) -> MergeReadiness {
+        if self.state != "OPEN" {
+            return MergeReadiness::Failed(vec![format!("PR is {}", self.state)]);
+        }
+
+        let mut failed = vec![];
+        let mut pending = vec![];
+        if self.status_check_rollup.is_empty() && expect_checks {
+            pending.push("no checks reported yet".to_string());
+        }
+        for check in &self.status_check_rollup {
+            match check.passed() {
+                Some(true) => {}
+                Some(false) => failed.push(check.display_name().to_string()),
+                None => pending.push(check.display_name().to_string()),
+            }
+        }
+
+        if require_review {
+            match self.review_decision.as_deref() {
+                Some("CHANGES_REQUESTED") => failed.push("changes requested".to_string()),
+                Some("REVIEW_REQUIRED") => pending.push("review required".to_string()),
+                _ => {}
+            }
+        }
+
+        if !failed.is_empty() {
+            MergeReadiness::Failed(failed)
+        } else if !pending.is_empty() {
+            MergeReadiness::Pending(pending)
+        } else {
+            MergeReadiness::Ready
+        }
+    }
+}
+
 #[async_trait]
 pub trait GithubOperationTrait {
-    fn create_pr(&self, msg: &str, tag: &str, branch: &str) -> anyhow::Result<String>;
+    fn create_pr(
+        &self,
+        msg: &str,
+        tag: &str,
+        branch: &str,
+        head_branch: &str,
+        options: &PullRequestOptions,
+    ) -> anyhow::Result<String>;
+
+    fn pr_status(&self, id: &str) -> anyhow::Result<PullRequestStatus>;
 
-    fn merge_pr(&self, id: &str) -> anyhow::Result<()>;
+    /// Merges the PR, and returns the SHA of the merged commit.
+    fn merge_pr(&self, id: &str, method: MergeMethod, admin: bool) -> anyhow::Result<String>;
 
     #[allow(dead_code)]
     async fn get_tag(&self, owner: &str, repo: &str, tag: &str) -> anyhow::Result<Tag>;
@@ -35,8 +275,20 @@ impl GithubCli {
 
 #[async_trait]
 impl GithubOperationTrait for GithubCli {
-    fn create_pr(&self, msg: &str, tag: &str, branch: &str) -> anyhow::Result<String> {
-        log::info!("Creating PR for tag {}, branch {}", tag, branch);
+    fn create_pr(
+        &self,
+        msg: &str,
+        tag: &str,
+        branch: &str,
+        head_branch: &str,
+        options: &PullRequestOptions,
+    ) -> anyhow::Result<String> {
+        log::info!(
+            "Creating PR for tag {}, branch {} from {}",
+            tag,
+            branch,
+            head_branch
+        );
 
         let repo_dir_path = self.repo.repo_dir_path();
 
@@ -45,8 +297,6 @@ impl GithubOperationTrait for GithubCli {
         } else {
             msg.to_string()
         };
-        let fork_branch = format!("pr-{}", tag);
-
         if String::from_utf8(cmd!("git", &repo_dir_path, &["status", "--porcelain"]).stdout)?
             .is_empty()
         {
@@ -55,34 +305,71 @@ impl GithubOperationTrait for GithubCli {
         }
 
         for args in [
-            vec!["checkout", "-b", &fork_branch],
+            vec!["checkout", "-b", head_branch],
             vec!["add", "."],
             vec!["commit", "-am", &msg, "-s"],
-            vec!["push", "-u", "--force", "origin", &fork_branch],
+            vec!["push", "-u", "--force", "origin", head_branch],
         ] {
             cmd!("git", &repo_dir_path, &args);
         }
 
-        let id = String::from_utf8(
-            cmd!(
-                "gh",
-                &repo_dir_path,
-                ["pr", "create", "--base", branch, "--fill", "--title", &msg]
-            )
-            .stdout,
-        )?;
+        let mut args = vec!["pr", "create", "--base", branch, "--title", &msg];
+        match options.body.as_ref() {
+            Some(body) => args.extend(["--body", body]),
+            None => args.push("--fill"),
+        }
+        for label in &options.labels {
+            args.extend(["--label", label]);
+        }
+        for reviewer in &options.reviewers {
+            args.extend(["--reviewer", reviewer]);
+        }
+        for assignee in &options.assignees {
+            args.extend(["--assignee", assignee]);
+        }
+        if let Some(milestone) = options.milestone.as_ref() {
+            args.extend(["--milestone", milestone]);
+        }
+
+        let id = String::from_utf8(cmd!("gh", &repo_dir_path, &args).stdout)?;
 
         Ok(id)
     }
 
-    fn merge_pr(&self, id: &str) -> anyhow::Result<()> {
-        cmd!(
+    fn pr_status(&self, id: &str) -> anyhow::Result<PullRequestStatus> {
+        let output = cmd!(
             "gh",
             &self.repo.repo_dir_path(),
-            ["pr", "merge", "--admin", "--rebase", "--delete-branch", id]
+            [
+                "pr",
+                "view",
+                id,
+                "--json",
+                "state,reviewDecision,statusCheckRollup,mergeCommit"
+            ]
         );
 
-        Ok(())
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    fn merge_pr(&self, id: &str, method: MergeMethod, admin: bool) -> anyhow::Result<String> {
+        let method = match method {
+            MergeMethod::Merge => "--merge",
+            MergeMethod::Squash => "--squash",
+            MergeMethod::Rebase => "--rebase",
+        };
+
+        let mut args = vec!["pr", "merge", method, "--delete-branch"];
+        if admin {
+            args.push("--admin");
+        }
+        args.push(id);
+        cmd!("gh", &self.repo.repo_dir_path(), &args);
+
+        self.pr_status(id)?
+            .merge_commit
+            .map(|it| it.oid)
+            .ok_or_else(|| anyhow!("no merged commit found for PR {}", id))
     }
 
     async fn get_tag(&self, owner: &str, repo: &str, tag: &str) -> anyhow::Result<Tag> {
@@ -96,3 +383,86 @@ impl GithubOperationTrait for GithubCli {
         Ok(tag)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness() {
+        let status = |checks: &str, review: &str| -> PullRequestStatus {
+            serde_json::from_str(&format!(
+                r#"{{"state": "OPEN", "reviewDecision": "{}", "statusCheckRollup": [{}]}}"#,
+                review, checks
+            ))
+            .unwrap()
+        };
+        let build = r#"{"__typename": "CheckRun", "name": "build", "status": "COMPLETED", "conclusion": "SUCCESS"}"#;
+        let dco = r#"{"__typename": "StatusContext", "context": "DCO", "state": "SUCCESS"}"#;
+        let lint_running = r#"{"__typename": "CheckRun", "name": "lint", "status": "IN_PROGRESS", "conclusion": ""}"#;
+        let test_failed = r#"{"__typename": "CheckRun", "name": "test", "status": "COMPLETED", "conclusion": "FAILURE"}"#;
+
+        let data = vec![
+            (
+                format!("{build},{dco}"),
+                "APPROVED",
+                true,
+                MergeReadiness::Ready,
+            ),
+            (format!("{build},{dco}"), "", true, MergeReadiness::Ready),
+            (
+                format!("{build},{lint_running}"),
+                "APPROVED",
+                true,
+                MergeReadiness::Pending(vec!["lint".to_string()]),
+            ),
+            (
+                format!("{build},{dco}"),
+                "REVIEW_REQUIRED",
+                true,
+                MergeReadiness::Pending(vec!["review required".to_string()]),
+            ),
+            (
+                format!("{build},{dco}"),
+                "REVIEW_REQUIRED",
+                false,
+                MergeReadiness::Ready,
+            ),
+            (
+                format!("{lint_running},{test_failed}"),
+                "APPROVED",
+                true,
+                MergeReadiness::Failed(vec!["test".to_string()]),
+            ),
+        ];
+
+        for (checks, review, require_review, expected) in data {
+            assert_eq!(
+                status(&checks, review).readiness(require_review, true),
+                expected,
+                "{checks} {review}"
+            );
+        }
+
+        let no_checks = status("", "APPROVED");
+        assert_eq!(
+            no_checks.readiness(true, true),
+            MergeReadiness::Pending(vec!["no checks reported yet".to_string()])
+        );
+        assert_eq!(no_checks.readiness(true, false), MergeReadiness::Ready);
+        assert_eq!(
+            status("", "REVIEW_REQUIRED").readiness(true, false),
+            MergeReadiness::Pending(vec!["review required".to_string()])
+        );
+
+        let merged: PullRequestStatus = serde_json::from_str(
+            r#"{"state": "MERGED", "reviewDecision": null, "statusCheckRollup": [], "mergeCommit": {"oid": "abc"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            merged.readiness(true, true),
+            MergeReadiness::Failed(_)
+        ));
+        assert_eq!(merged.merge_commit.unwrap().oid, "abc");
+    }
+}
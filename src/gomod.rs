@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref REQUIRE_REG: Regex =
+        Regex::new(r"^(?:require\s+)?([^\s()]+)\s+(v\S+)\s*(//.*)?$").unwrap();
+}
+
+/// A module added, removed or bumped between two go.mod files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ModuleChange {
+    pub(crate) module: String,
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+}
+
+/// Parses the direct requirements of a go.mod file into module versions.
+pub(crate) fn parse_requires(content: &str) -> BTreeMap<String, String> {
+    let mut requires = BTreeMap::new();
+    let mut in_require_block = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if in_require_block {
+            if line.starts_with(')') {
+                in_require_block = false;
+                continue;
+            }
+        } else if line.starts_with("require") {
+            if line.trim_start_matches("require").trim() == "(" {
+                in_require_block = true;
+                continue;
+            }
+        } else {
+            continue;
+        }
+
+        if let Some(caps) = REQUIRE_REG.captures(line) {
+            let is_indirect = caps
+                .get(3)
+                .is_some_and(|it| it.as_str().contains("indirect"));
+
+            if !is_indirect {
+                requires.insert(caps[1].to_string(), caps[2].to_string());
+            }
+        }
+    }
+
+    requires
+}
+
+/// Diffs the direct requirements of two go.mod files.
+pub(crate) fn diff_requires(prev_content: &str, content: &str) -> Vec<ModuleChange> {
+    let prev_requires = parse_requires(prev_content);
+    let requires = parse_requires(content);
+    let mut changes = vec![];
+
+    for (module, version) in &requires {
+        match prev_requires.get(module) {
+            Some(prev_version) if prev_version == version => {}
+            prev_version => changes.push(ModuleChange {
+                module: module.clone(),
+                from: prev_version.cloned(),
+                to: Some(version.clone()),
+            }),
+        }
+    }
+
+    for (module, prev_version) in &prev_requires {
+        if !requires.contains_key(module) {
+            changes.push(ModuleChange {
+                module: module.clone(),
+                from: Some(prev_version.clone()),
+                to: None,
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.module.cmp(&b.module));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_requires() {
+        let prev_content = indoc! {"
+            module github.com/longhorn/longhorn-manager
+
+            go 1.21
+
+            require github.com/longhorn/backupstore v0.0.0-20240101
+
+            require (
+                github.com/longhorn/go-common-libs v0.0.0-20240101
+                github.com/pkg/errors v0.9.1
+                golang.org/x/net v0.17.0 // indirect
+            )
+        "};
+        let content = indoc! {"
+            module github.com/longhorn/longhorn-manager
+
+            go 1.21
+
+            require (
+                github.com/longhorn/backupstore v0.0.0-20240301
+                github.com/longhorn/go-common-libs v0.0.0-20240101
+                github.com/sirupsen/logrus v1.9.3
+                golang.org/x/net v0.23.0 // indirect
+            )
+        "};
+
+        let changes = diff_requires(prev_content, content);
+
+        assert_eq!(
+            changes,
+            vec![
+                ModuleChange {
+                    module: "github.com/longhorn/backupstore".to_string(),
+                    from: Some("v0.0.0-20240101".to_string()),
+                    to: Some("v0.0.0-20240301".to_string()),
+                },
+                ModuleChange {
+                    module: "github.com/pkg/errors".to_string(),
+                    from: Some("v0.9.1".to_string()),
+                    to: None,
+                },
+                ModuleChange {
+                    module: "github.com/sirupsen/logrus".to_string(),
+                    from: None,
+                    to: Some("v1.9.3".to_string()),
+                },
+            ]
+        );
+    }
+}
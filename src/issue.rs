@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+use lazy_static::lazy_static;
+use octocrab::models::IssueState;
+use regex::Regex;
+
+use crate::github::github_client;
+
+lazy_static! {
+    static ref ISSUE_URL_REG: Regex =
+        Regex::new(r"https://github\.com/([\w.-]+)/([\w.-]+)/(?:issues|pull)/(\d+)").unwrap();
+    static ref REPO_ISSUE_REG: Regex =
+        Regex::new(r"(?:^|[^\w/.-])([\w.-]+)/([\w.-]+)#(\d+)\b").unwrap();
+    static ref ISSUE_REG: Regex = Regex::new(r"(?:^|[^\w/#])#(\d+)\b").unwrap();
+}
+
+/// A reference to an issue, like #1234, longhorn/longhorn#1234 or the issue URL.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct IssueRef {
+    pub(crate) owner: String,
+    pub(crate) repo: String,
+    pub(crate) number: u64,
+}
+
+impl Display for IssueRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+/// An issue referenced by commits, with the title and state if resolved.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LinkedIssue {
+    pub(crate) reference: IssueRef,
+    pub(crate) html_url: String,
+    pub(crate) title: Option<String>,
+    pub(crate) state: Option<String>,
+}
+
+impl LinkedIssue {
+    pub(crate) fn new(reference: IssueRef) -> Self {
+        Self {
+            html_url: format!(
+                "https://github.com/{}/{}/issues/{}",
+                reference.owner, reference.repo, reference.number
+            ),
+            reference,
+            title: None,
+            state: None,
+        }
+    }
+}
+
+/// Parses the issue references of a commit message. #N refers to an issue of the given repo.
+pub(crate) fn parse_issue_refs(message: &str, owner: &str, repo: &str) -> Vec<IssueRef> {
+    let mut refs = BTreeSet::new();
+    let mut message = message.to_string();
+
+    // Skip the numbers overflowing u64, which can't be issues
+    refs.extend(
+        ISSUE_URL_REG
+            .captures_iter(&message.clone())
+            .filter_map(|caps| {
+                Some(IssueRef {
+                    owner: caps[1].to_string(),
+                    repo: caps[2].to_string(),
+                    number: caps[3].parse().ok()?,
+                })
+            }),
+    );
+    message = ISSUE_URL_REG.replace_all(&message, "").to_string();
+
+    refs.extend(
+        REPO_ISSUE_REG
+            .captures_iter(&message.clone())
+            .filter_map(|caps| {
+                Some(IssueRef {
+                    owner: caps[1].to_string(),
+                    repo: caps[2].to_string(),
+                    number: caps[3].parse().ok()?,
+                })
+            }),
+    );
+    message = REPO_ISSUE_REG.replace_all(&message, "").to_string();
+
+    refs.extend(ISSUE_REG.captures_iter(&message).filter_map(|caps| {
+        Some(IssueRef {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: caps[1].parse().ok()?,
+        })
+    }));
+
+    refs.into_iter().collect()
+}
+
+/// Fetches the title and state of the issue. Returns `None` if the reference is a pull request.
+pub(crate) async fn resolve_issue(reference: &IssueRef) -> anyhow::Result<Option<LinkedIssue>> {
+    let issue = github_client()
+        .issues(&reference.owner, &reference.repo)
+        .get(reference.number)
+        .await?;
+
+    if issue.pull_request.is_some() {
+        return Ok(None);
+    }
+
+    Ok(Some(LinkedIssue {
+        reference: reference.clone(),
+        html_url: issue.html_url.to_string(),
+        title: Some(issue.title),
+        state: Some(match issue.state {
+            IssueState::Open => "open".to_string(),
+            _ => "closed".to_string(),
+        }),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_issue_refs() {
+        let data = vec![
+            ("fix: foo (#12)", vec!["longhorn/longhorn-manager#12"]),
+            (
+                "Fix foo (longhorn/longhorn#1234)",
+                vec!["longhorn/longhorn#1234"],
+            ),
+            (
+                "fix: bar\n\nref: longhorn/longhorn#1234\nhttps://github.com/longhorn/longhorn/issues/5678",
+                vec!["longhorn/longhorn#1234", "longhorn/longhorn#5678"],
+            ),
+            ("chore: update color #fff and #12abc", vec![]),
+            (
+                "fix: foo #99999999999999999999999 (#12)\n\nlonghorn/longhorn#99999999999999999999999\nhttps://github.com/longhorn/longhorn/issues/99999999999999999999999",
+                vec!["longhorn/longhorn-manager#12"],
+            ),
+        ];
+
+        for (message, expected) in data {
+            let refs: Vec<String> = parse_issue_refs(message, "longhorn", "longhorn-manager")
+                .iter()
+                .map(|it| it.to_string())
+                .collect();
+
+            assert_eq!(refs, expected);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::{env, fs};
 
 use anyhow::anyhow;
@@ -5,8 +6,16 @@ use clap::{Parser, Subcommand};
 
 use octocrab::OctocrabBuilder;
 
+use crate::cmds::audit::AuditArgs;
 use crate::cmds::changelog::ChangelogArgs;
+use crate::cmds::chart::ChartArgs;
+use crate::cmds::contributors::ContributorsArgs;
+use crate::cmds::crd::CrdDiffArgs;
+use crate::cmds::diff::DiffArgs;
+use crate::cmds::images::{CheckImagesArgs, CheckRegistryArgs};
+use crate::cmds::pending::PendingArgs;
 use crate::cmds::pr::PrArgs;
+use crate::cmds::reconcile::ReconcileArgs;
 use crate::cmds::release::ReleaseArgs;
 use crate::cmds::tag::TagArgs;
 use crate::cmds::CliCommand;
@@ -15,10 +24,17 @@ use crate::global::GITHUB_CLIENT;
 
 mod cmds;
 mod common;
+mod config;
+mod crd;
 mod git;
 mod github;
 mod global;
+mod gomod;
+mod issue;
 mod macros;
+mod manifest;
+mod registry;
+mod yamlpath;
 
 #[derive(Parser)]
 #[command(author, version = env!("VERSION"), about)]
@@ -36,6 +52,9 @@ pub struct Cli {
     )]
     log_level: String,
 
+    #[arg(global = true, long, env = "RENOTE_CONFIG", help = "Config file")]
+    config: Option<PathBuf>,
+
     #[arg(global = true, long, env, help = "GitHub Token")]
     github_token: Option<String>,
 
@@ -54,8 +73,17 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    Audit(AuditArgs),
     Changelog(ChangelogArgs),
+    Chart(ChartArgs),
+    CheckImages(CheckImagesArgs),
+    CheckRegistry(CheckRegistryArgs),
+    Contributors(ContributorsArgs),
+    CrdDiff(CrdDiffArgs),
+    Diff(DiffArgs),
+    Pending(PendingArgs),
     Pr(PrArgs),
+    Reconcile(ReconcileArgs),
     Release(ReleaseArgs),
     Tag(TagArgs),
 }
@@ -88,8 +116,17 @@ async fn main() -> anyhow::Result<()> {
     execute(cli.pre_hook.as_ref(), cli.pre_hook_args.as_ref())?;
 
     match &cli.command {
+        Commands::Audit(args) => args.run(&cli).await,
         Commands::Changelog(args) => args.run(&cli).await,
+        Commands::Chart(args) => args.run(&cli).await,
+        Commands::CheckImages(args) => args.run(&cli).await,
+        Commands::CheckRegistry(args) => args.run(&cli).await,
+        Commands::Contributors(args) => args.run(&cli).await,
+        Commands::CrdDiff(args) => args.run(&cli).await,
+        Commands::Diff(args) => args.run(&cli).await,
+        Commands::Pending(args) => args.run(&cli).await,
         Commands::Pr(args) => args.run(&cli).await,
+        Commands::Reconcile(args) => args.run(&cli).await,
         Commands::Release(args) => args.run(&cli).await,
         Commands::Tag(args) => args.run(&cli).await,
     }?;
@@ -0,0 +1,399 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+lazy_static! {
+    static ref IMAGE_REG: Regex = Regex::new(r"^\s*(\S+/[^\s:]+):(\S+)\s*$").unwrap();
+}
+
+/// A value added, removed or changed between two manifests.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct ValueChange {
+    pub(crate) key: String,
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+}
+
+/// Flattens a YAML document into dotted keys and scalar values (e.g. image.longhorn.manager.tag).
+pub(crate) fn flatten_yaml(content: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+
+    if !content.trim().is_empty() {
+        let value: Value = serde_yaml::from_str(content)?;
+        flatten_value("", &value, &mut values);
+    }
+
+    Ok(values)
+}
+
+fn flatten_value(prefix: &str, value: &Value, values: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (k, v) in mapping {
+                let key = scalar_to_string(k).unwrap_or_default();
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                flatten_value(&key, v, values);
+            }
+        }
+        Value::Sequence(sequence) if !sequence.is_empty() => {
+            for (i, v) in sequence.iter().enumerate() {
+                flatten_value(&format!("{prefix}[{i}]"), v, values);
+            }
+        }
+        Value::Mapping(_) => {
+            values.insert(prefix.to_string(), "{}".to_string());
+        }
+        Value::Sequence(_) => {
+            values.insert(prefix.to_string(), "[]".to_string());
+        }
+        Value::Tagged(tagged) => flatten_value(prefix, &tagged.value, values),
+        _ => {
+            values.insert(
+                prefix.to_string(),
+                scalar_to_string(value).unwrap_or_default(),
+            );
+        }
+    }
+}
+
+pub(crate) fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("null".to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Parses an image list like deploy/longhorn-images.txt into image names and tags.
+pub(crate) fn parse_image_list(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| IMAGE_REG.captures(line))
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+/// An image referenced by a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub(crate) struct ImageRef {
+    pub(crate) file: String,
+    pub(crate) image: String,
+    pub(crate) tag: String,
+}
+
+/// Finds the references of the images starting with the prefix (e.g. longhornio/), either
+/// written as `image:tag`, or split into repository and tag like in chart/values.yaml and
+/// chart/questions.yaml.
+pub(crate) fn find_image_refs(file: &str, content: &str, prefix: &str) -> Vec<ImageRef> {
+    let mut refs = BTreeSet::new();
+    let mut insert = |image: &str, tag: &str| {
+        refs.insert(ImageRef {
+            file: file.to_string(),
+            image: image.to_string(),
+            tag: tag.to_string(),
+        });
+    };
+
+    let reg = Regex::new(&format!(r"({}[\w./-]+):([\w.-]+)", regex::escape(prefix))).unwrap();
+    for caps in reg.captures_iter(content) {
+        insert(&caps[1], &caps[2]);
+    }
+
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let Ok(value) = Value::deserialize(document) else {
+            break;
+        };
+
+        let mut pairs = vec![];
+        collect_repository_tags(&value, &mut pairs);
+        for (image, tag) in pairs {
+            if image.starts_with(prefix) {
+                insert(&image, &tag);
+            }
+        }
+    }
+
+    refs.into_iter().collect()
+}
+
+/// Collects the repository and tag pairs of mappings like `{repository: x, tag: y}`, and of
+/// question variables like `image.longhorn.manager.repository` and `image.longhorn.manager.tag`.
+fn collect_repository_tags(value: &Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Mapping(mapping) => {
+            if let (Some(repository), Some(tag)) = (
+                mapping.get("repository").and_then(scalar_to_string),
+                mapping.get("tag").and_then(scalar_to_string),
+            ) {
+                pairs.push((repository, tag));
+            }
+
+            for v in mapping.values() {
+                collect_repository_tags(v, pairs);
+            }
+        }
+        Value::Sequence(sequence) => {
+            let variables: HashMap<String, String> = sequence
+                .iter()
+                .filter_map(|it| {
+                    Some((
+                        it.get("variable").and_then(scalar_to_string)?,
+                        it.get("default").and_then(scalar_to_string)?,
+                    ))
+                })
+                .collect();
+
+            for (variable, repository) in &variables {
+                if let Some(prefix) = variable.strip_suffix(".repository") {
+                    if let Some(tag) = variables.get(&format!("{prefix}.tag")) {
+                        pairs.push((repository.clone(), tag.clone()));
+                    }
+                }
+            }
+
+            for v in sequence {
+                collect_repository_tags(v, pairs);
+            }
+        }
+        Value::Tagged(tagged) => collect_repository_tags(&tagged.value, pairs),
+        _ => {}
+    }
+}
+
+/// Checks the image references are consistent between files, and the release images are tagged
+/// with the release tag. Returns the problems found.
+pub(crate) fn check_image_refs(
+    refs: &[ImageRef],
+    tag: &str,
+    release_images: &Regex,
+) -> Vec<String> {
+    let mut tags_by_image: BTreeMap<&str, BTreeMap<&str, BTreeSet<&str>>> = BTreeMap::new();
+    for r in refs {
+        tags_by_image
+            .entry(&r.image)
+            .or_default()
+            .entry(&r.tag)
+            .or_default()
+            .insert(&r.file);
+    }
+
+    let mut problems = vec![];
+    for (image, tags) in &tags_by_image {
+        let render = |tag: &str, files: &BTreeSet<&str>| {
+            format!(
+                "{}:{} in {}",
+                image,
+                tag,
+                files.iter().cloned().collect::<Vec<_>>().join(", ")
+            )
+        };
+
+        if release_images.is_match(image) {
+            for (image_tag, files) in tags {
+                if *image_tag != tag {
+                    problems.push(format!(
+                        "{} does not match the release tag {}",
+                        render(image_tag, files),
+                        tag
+                    ));
+                }
+            }
+        }
+
+        if tags.len() > 1 {
+            problems.push(format!(
+                "{} has different tags: {}",
+                image,
+                tags.iter()
+                    .map(|(tag, files)| render(tag, files))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Diffs two sets of values by key.
+pub(crate) fn diff_values(
+    from: &BTreeMap<String, String>,
+    to: &BTreeMap<String, String>,
+) -> Vec<ValueChange> {
+    let mut changes = vec![];
+
+    for (key, value) in to {
+        match from.get(key) {
+            Some(prev_value) if prev_value == value => {}
+            prev_value => changes.push(ValueChange {
+                key: key.clone(),
+                from: prev_value.cloned(),
+                to: Some(value.clone()),
+            }),
+        }
+    }
+
+    for (key, prev_value) in from {
+        if !to.contains_key(key) {
+            changes.push(ValueChange {
+                key: key.clone(),
+                from: Some(prev_value.clone()),
+                to: None,
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use maplit::btreemap;
+
+    use super::*;
+
+    #[test]
+    fn test_flatten_yaml() -> anyhow::Result<()> {
+        let content = indoc! {"
+            image:
+              longhorn:
+                manager:
+                  repository: longhornio/longhorn-manager
+                  tag: master-head
+            persistence:
+              defaultClassReplicaCount: 3
+              recurringJobSelector:
+                enable: false
+            tolerations: []
+            args:
+            - --debug
+        "};
+
+        assert_eq!(
+            flatten_yaml(content)?,
+            btreemap! {
+                "image.longhorn.manager.repository".to_string() => "longhornio/longhorn-manager".to_string(),
+                "image.longhorn.manager.tag".to_string() => "master-head".to_string(),
+                "persistence.defaultClassReplicaCount".to_string() => "3".to_string(),
+                "persistence.recurringJobSelector.enable".to_string() => "false".to_string(),
+                "tolerations".to_string() => "[]".to_string(),
+                "args[0]".to_string() => "--debug".to_string(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_values() {
+        let from = parse_image_list(indoc! {"
+            longhornio/longhorn-manager:v1.6.0
+            longhornio/longhorn-ui:v1.6.0
+            longhornio/csi-attacher:v4.4.2
+        "});
+        let to = parse_image_list(indoc! {"
+            longhornio/longhorn-manager:v1.6.1
+            longhornio/longhorn-ui:v1.6.1
+            longhornio/csi-resizer:v1.9.2
+        "});
+
+        assert_eq!(
+            diff_values(&from, &to),
+            vec![
+                ValueChange {
+                    key: "longhornio/csi-attacher".to_string(),
+                    from: Some("v4.4.2".to_string()),
+                    to: None,
+                },
+                ValueChange {
+                    key: "longhornio/csi-resizer".to_string(),
+                    from: None,
+                    to: Some("v1.9.2".to_string()),
+                },
+                ValueChange {
+                    key: "longhornio/longhorn-manager".to_string(),
+                    from: Some("v1.6.0".to_string()),
+                    to: Some("v1.6.1".to_string()),
+                },
+                ValueChange {
+                    key: "longhornio/longhorn-ui".to_string(),
+                    from: Some("v1.6.0".to_string()),
+                    to: Some("v1.6.1".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_image_refs() -> anyhow::Result<()> {
+        let values = indoc! {"
+            image:
+              longhorn:
+                manager:
+                  repository: longhornio/longhorn-manager
+                  tag: v1.6.1
+                engine:
+                  repository: longhornio/longhorn-engine
+                  tag: v1.6.0
+              csi:
+                attacher:
+                  repository: longhornio/csi-attacher
+                  tag: v4.4.2
+        "};
+        let questions = indoc! {"
+            questions:
+            - variable: image.defaultImage
+              subquestions:
+              - variable: image.longhorn.manager.repository
+                default: longhornio/longhorn-manager
+              - variable: image.longhorn.manager.tag
+                default: v1.6.1
+        "};
+        let images = indoc! {"
+            longhornio/longhorn-manager:v1.6.1
+            longhornio/longhorn-engine:v1.6.1
+            longhornio/csi-attacher:v4.4.1
+        "};
+
+        let mut refs = find_image_refs("chart/values.yaml", values, "longhornio/");
+        refs.extend(find_image_refs(
+            "chart/questions.yaml",
+            questions,
+            "longhornio/",
+        ));
+        refs.extend(find_image_refs(
+            "deploy/longhorn-images.txt",
+            images,
+            "longhornio/",
+        ));
+        assert_eq!(refs.len(), 7);
+
+        let problems = check_image_refs(
+            &refs,
+            "v1.6.1",
+            &Regex::new(r"^longhornio/(longhorn-[\w-]+|backing-image-manager)$")?,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "longhornio/csi-attacher has different tags: longhornio/csi-attacher:v4.4.1 in deploy/longhorn-images.txt; longhornio/csi-attacher:v4.4.2 in chart/values.yaml",
+                "longhornio/longhorn-engine:v1.6.0 in chart/values.yaml does not match the release tag v1.6.1",
+                "longhornio/longhorn-engine has different tags: longhornio/longhorn-engine:v1.6.0 in chart/values.yaml; longhornio/longhorn-engine:v1.6.1 in deploy/longhorn-images.txt",
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use reqwest::header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+/// The media types of the manifests accepted when resolving a tag, preferring indexes of
+/// multi-arch images.
+const MANIFEST_MEDIA_TYPES: [&str; 4] = [
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+
+const DIGEST_HEADER: &str = "Docker-Content-Digest";
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// A client of the OCI distribution API, e.g. https://registry-1.docker.io.
+pub(crate) struct RegistryClient {
+    endpoint: String,
+    client: Client,
+    /// Anonymous bearer tokens by repository.
+    tokens: HashMap<String, String>,
+}
+
+impl RegistryClient {
+    pub(crate) fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            client: Client::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Returns the digest of the image tag, or None if the tag does not exist.
+    pub(crate) async fn manifest_digest(
+        &mut self,
+        repository: &str,
+        tag: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let url = format!("{}/v2/{}/manifests/{}", self.endpoint, repository, tag);
+
+        let mut resp = self.head_manifest(&url, repository).await?;
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            let challenge = resp
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|it| it.to_str().ok())
+                .ok_or_else(|| anyhow!("{} requires authentication without a challenge", url))?;
+            let token = self.fetch_token(challenge).await?;
+            self.tokens.insert(repository.to_string(), token);
+
+            resp = self.head_manifest(&url, repository).await?;
+        }
+
+        match resp.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let digest = resp
+                    .headers()
+                    .get(DIGEST_HEADER)
+                    .and_then(|it| it.to_str().ok())
+                    .ok_or_else(|| anyhow!("{} returned no {} header", url, DIGEST_HEADER))?;
+
+                Ok(Some(digest.to_string()))
+            }
+            status => Err(anyhow!("failed to resolve {}: {}", url, status)),
+        }
+    }
+
+    async fn head_manifest(
+        &self,
+        url: &str,
+        repository: &str,
+    ) -> anyhow::Result<reqwest::Response> {
+        let mut req = self
+            .client
+            .head(url)
+            .header(ACCEPT, MANIFEST_MEDIA_TYPES.join(", "));
+        if let Some(token) = self.tokens.get(repository) {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        Ok(req.send().await?)
+    }
+
+    /// Fetches an anonymous token for the bearer challenge of the registry.
+    async fn fetch_token(&self, challenge: &str) -> anyhow::Result<String> {
+        let params = parse_bearer_challenge(challenge)
+            .ok_or_else(|| anyhow!("unsupported authentication challenge: {}", challenge))?;
+        let realm = params
+            .get("realm")
+            .ok_or_else(|| anyhow!("no realm in authentication challenge: {}", challenge))?;
+        let query: Vec<(&String, &String)> = params.iter().filter(|(k, _)| *k != "realm").collect();
+
+        let resp: TokenResponse = self
+            .client
+            .get(realm)
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        resp.token
+            .or(resp.access_token)
+            .ok_or_else(|| anyhow!("no token returned by {}", realm))
+    }
+}
+
+/// Parses a challenge like `Bearer realm="https://auth.docker.io/token",service="registry.docker.io"`.
+fn parse_bearer_challenge(challenge: &str) -> Option<HashMap<String, String>> {
+    let (scheme, params) = challenge.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut result = HashMap::new();
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let value = value.trim_start();
+
+        let (value, remaining) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+
+        result.insert(key.trim().to_string(), value.to_string());
+        rest = remaining.trim_start_matches([',', ' ']);
+    }
+
+    Some(result)
+}
+
+/// Splits an image into the registry host if any, and the repository in the registry,
+/// e.g. `quay.io/org/app` into `quay.io` and `org/app`, and `nginx` into no host and
+/// `library/nginx` of Docker Hub.
+pub(crate) fn split_image(image: &str) -> (Option<&str>, String) {
+    match image.split_once('/') {
+        Some((host, repository)) if host.contains(['.', ':']) || host == "localhost" => {
+            (Some(host), repository.to_string())
+        }
+        Some(_) => (None, image.to_string()),
+        None => (None, format!("library/{}", image)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bearer_challenge() {
+        let params = parse_bearer_challenge(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:longhornio/longhorn-manager:pull""#,
+        )
+        .unwrap();
+
+        assert_eq!(params["realm"], "https://auth.docker.io/token");
+        assert_eq!(params["service"], "registry.docker.io");
+        assert_eq!(
+            params["scope"],
+            "repository:longhornio/longhorn-manager:pull"
+        );
+
+        assert!(parse_bearer_challenge(r#"Basic realm="registry""#).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_digest() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let manifests = "/v2/longhornio/longhorn-manager/manifests";
+
+        let challenge = server
+            .mock("HEAD", format!("{manifests}/v1.6.0").as_str())
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(401)
+            .with_header(
+                "www-authenticate",
+                &format!(
+                    r#"Bearer realm="{}/token",service="registry",scope="repository:longhornio/longhorn-manager:pull""#,
+                    server.url()
+                ),
+            )
+            .create_async()
+            .await;
+        let token = server
+            .mock("GET", "/token")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("service".into(), "registry".into()),
+                mockito::Matcher::UrlEncoded(
+                    "scope".into(),
+                    "repository:longhornio/longhorn-manager:pull".into(),
+                ),
+            ]))
+            .with_body(r#"{"token":"secret"}"#)
+            .create_async()
+            .await;
+        let found = server
+            .mock("HEAD", format!("{manifests}/v1.6.0").as_str())
+            .match_header("authorization", "Bearer secret")
+            .with_header(DIGEST_HEADER, "sha256:0123456789abcdef")
+            .create_async()
+            .await;
+        let missing = server
+            .mock("HEAD", format!("{manifests}/v0.0.0").as_str())
+            .match_header("authorization", "Bearer secret")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let mut client = RegistryClient::new(&server.url());
+        assert_eq!(
+            client
+                .manifest_digest("longhornio/longhorn-manager", "v1.6.0")
+                .await?
+                .as_deref(),
+            Some("sha256:0123456789abcdef")
+        );
+        // The token of the repository is reused
+        assert_eq!(
+            client
+                .manifest_digest("longhornio/longhorn-manager", "v0.0.0")
+                .await?,
+            None
+        );
+
+        for mock in [challenge, token, found, missing] {
+            mock.assert_async().await;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_image() {
+        let data = vec![
+            (
+                "longhornio/longhorn-manager",
+                (None, "longhornio/longhorn-manager"),
+            ),
+            (
+                "registry.k8s.io/sig-storage/csi-attacher",
+                (Some("registry.k8s.io"), "sig-storage/csi-attacher"),
+            ),
+            (
+                "localhost:5000/longhornio/longhorn-ui",
+                (Some("localhost:5000"), "longhornio/longhorn-ui"),
+            ),
+            ("nginx", (None, "library/nginx")),
+        ];
+
+        for (image, (host, repository)) in data {
+            assert_eq!(split_image(image), (host, repository.to_string()));
+        }
+    }
+}
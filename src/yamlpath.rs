@@ -0,0 +1,363 @@
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref MAPPING_ENTRY_REG: Regex =
+        Regex::new(r#"^("[^"]*"|'[^']*'|[^\s#'"\-][^:#]*?|-[^\s:#][^:#]*?)\s*:(\s|$)"#).unwrap();
+}
+
+/// A segment of a YAML path like `questions[variable=image.longhorn.engine.tag].default`.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    /// Selects the sequence item whose field equals the value.
+    Select(String, String),
+}
+
+fn parse_path(path: &str) -> anyhow::Result<Vec<Segment>> {
+    let mut segments = vec![];
+    let mut key = String::new();
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if !key.is_empty() {
+                    segments.push(Segment::Key(std::mem::take(&mut key)));
+                }
+            }
+            '[' => {
+                if !key.is_empty() {
+                    segments.push(Segment::Key(std::mem::take(&mut key)));
+                }
+
+                let selector: String = chars.by_ref().take_while(|it| *it != ']').collect();
+                segments.push(match selector.split_once('=') {
+                    Some((field, value)) => {
+                        Segment::Select(field.trim().to_string(), value.trim().to_string())
+                    }
+                    None => Segment::Index(
+                        selector
+                            .trim()
+                            .parse()
+                            .map_err(|_| anyhow!("invalid index [{}] in {}", selector, path))?,
+                    ),
+                });
+            }
+            _ => key.push(c),
+        }
+    }
+    if !key.is_empty() {
+        segments.push(Segment::Key(key));
+    }
+
+    if segments.is_empty() {
+        return Err(anyhow!("empty YAML path"));
+    }
+
+    Ok(segments)
+}
+
+/// A block mapping or sequence starting at the column of the line, ending before the end line.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    line: usize,
+    col: usize,
+    end: usize,
+}
+
+/// The position of a scalar value in a line.
+#[derive(Clone, Copy, Debug)]
+struct Scalar {
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Node {
+    Block(Block),
+    Scalar(Scalar),
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+
+    !trimmed.is_empty() && !trimmed.starts_with('#') && trimmed != "---" && trimmed != "..."
+}
+
+fn unquote(value: &str) -> &str {
+    if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+/// Returns the end of the scalar starting at the column, excluding the trailing comment.
+fn scalar_end(line: &str, start: usize) -> usize {
+    let text = &line[start..];
+
+    if let Some(quote) = text.chars().next().filter(|it| *it == '"' || *it == '\'') {
+        let mut escaped = false;
+        for (i, c) in text.char_indices().skip(1) {
+            if c == quote && !escaped {
+                return start + i + 1;
+            }
+            escaped = quote == '"' && c == '\\' && !escaped;
+        }
+    }
+
+    let end = text.find(" #").unwrap_or(text.len());
+    start + text[..end].trim_end().len()
+}
+
+/// Returns the node whose text starts at the column of the line.
+fn node_at(lines: &[&str], line: usize, col: usize, end: usize) -> Node {
+    let text = &lines[line][col..];
+
+    if text.starts_with('-') && (text.len() == 1 || text[1..].starts_with(' '))
+        || MAPPING_ENTRY_REG.is_match(text)
+    {
+        Node::Block(Block { line, col, end })
+    } else {
+        Node::Scalar(Scalar {
+            line,
+            start: col,
+            end: scalar_end(lines[line], col),
+        })
+    }
+}
+
+/// Returns the first content line in the range.
+fn first_content_line(lines: &[&str], start: usize, end: usize) -> Option<usize> {
+    (start..end).find(|it| is_content(lines[*it]))
+}
+
+fn mapping_value(lines: &[&str], block: Block, key: &str) -> Option<Node> {
+    let entries = std::iter::once(block.line).chain((block.line + 1..block.end).filter(|it| {
+        let line = lines[*it];
+        is_content(line) && indent(line) == block.col && !line[block.col..].starts_with('-')
+    }));
+
+    for line in entries {
+        let text = &lines[line][block.col..];
+        let Some(caps) = MAPPING_ENTRY_REG.captures(text) else {
+            continue;
+        };
+        if unquote(caps[1].trim()) != key {
+            continue;
+        }
+
+        let value_col = block.col + caps[0].len();
+        let rest = lines[line][value_col..].trim();
+        if !rest.is_empty() && !rest.starts_with('#') && !rest.starts_with(['|', '>']) {
+            let start = lines[line].len() - lines[line][value_col..].trim_start().len();
+            return Some(Node::Scalar(Scalar {
+                line,
+                start,
+                end: scalar_end(lines[line], start),
+            }));
+        }
+
+        let end = (line + 1..block.end)
+            .find(|it| {
+                let l = lines[*it];
+                is_content(l)
+                    && (indent(l) < block.col
+                        || (indent(l) == block.col && !l[block.col..].starts_with('-')))
+            })
+            .unwrap_or(block.end);
+        let child = first_content_line(lines, line + 1, end)?;
+
+        return Some(node_at(lines, child, indent(lines[child]), end));
+    }
+
+    None
+}
+
+fn sequence_items(lines: &[&str], block: Block) -> Vec<Node> {
+    let starts: Vec<usize> = (block.line..block.end)
+        .filter(|it| {
+            let line = lines[*it];
+            is_content(line) && indent(line) == block.col && line[block.col..].starts_with('-')
+        })
+        .collect();
+
+    let mut items = vec![];
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(block.end);
+        let line = lines[*start];
+        let col = block.col + 1 + indent(&line[block.col + 1..]);
+
+        if col < line.len() {
+            items.push(node_at(lines, *start, col, end));
+        } else if let Some(child) = first_content_line(lines, start + 1, end) {
+            items.push(node_at(lines, child, indent(lines[child]), end));
+        }
+    }
+
+    items
+}
+
+fn scalar_text<'a>(lines: &[&'a str], scalar: Scalar) -> &'a str {
+    &lines[scalar.line][scalar.start..scalar.end]
+}
+
+fn find(lines: &[&str], path: &str) -> anyhow::Result<Scalar> {
+    let segments = parse_path(path)?;
+    let not_found = || anyhow!("YAML path {} not found", path);
+
+    let first = first_content_line(lines, 0, lines.len()).ok_or_else(not_found)?;
+    let mut node = node_at(lines, first, indent(lines[first]), lines.len());
+
+    for segment in &segments {
+        let Node::Block(block) = node else {
+            return Err(not_found());
+        };
+
+        node = match segment {
+            Segment::Key(key) => mapping_value(lines, block, key),
+            Segment::Index(index) => sequence_items(lines, block).get(*index).copied(),
+            Segment::Select(field, value) => {
+                sequence_items(lines, block).into_iter().find(|item| {
+                    let Node::Block(item) = item else {
+                        return false;
+                    };
+
+                    matches!(
+                        mapping_value(lines, *item, field),
+                        Some(Node::Scalar(scalar)) if unquote(scalar_text(lines, scalar)) == value
+                    )
+                })
+            }
+        }
+        .ok_or_else(not_found)?;
+    }
+
+    match node {
+        Node::Scalar(scalar) => Ok(scalar),
+        Node::Block(_) => Err(anyhow!("YAML path {} is not a scalar", path)),
+    }
+}
+
+/// Returns the scalar value at the YAML path, e.g. `image.longhorn.manager.tag`.
+pub(crate) fn get_yaml_path(content: &str, path: &str) -> anyhow::Result<String> {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let scalar = find(&lines, path)?;
+
+    Ok(unquote(scalar_text(&lines, scalar)).to_string())
+}
+
+/// Replaces the scalar value at the YAML path, keeping comments, ordering and quoting of the content.
+pub(crate) fn set_yaml_path(content: &str, path: &str, value: &str) -> anyhow::Result<String> {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let scalar = find(&lines, path)?;
+
+    let old_value = scalar_text(&lines, scalar);
+    let new_value = match old_value.chars().next() {
+        Some(quote @ ('"' | '\'')) if old_value.len() >= 2 => format!("{quote}{value}{quote}"),
+        _ => value.to_string(),
+    };
+
+    let line = lines[scalar.line];
+    let new_line = format!(
+        "{}{}{}",
+        &line[..scalar.start],
+        new_value,
+        &line[scalar.end..]
+    );
+
+    let mut lines: Vec<String> = lines.into_iter().map(|it| it.to_string()).collect();
+    lines[scalar.line] = new_line;
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_set_yaml_path() -> anyhow::Result<()> {
+        let values = indoc! {r#"
+            # Default values for longhorn.
+            image:
+              longhorn:
+                engine:
+                  repository: longhornio/longhorn-engine
+                  tag: master-head # engine tag
+                manager:
+                  repository: longhornio/longhorn-manager
+                  tag: "master-head"
+              csi:
+                attacher:
+                  repository: longhornio/csi-attacher
+                  tag: v4.4.2
+            persistence:
+              defaultClass: true
+        "#};
+        let questions = indoc! {"
+            categories:
+            - storage
+            questions:
+            - variable: image.defaultImage
+              default: \"true\"
+              subquestions:
+              - variable: image.longhorn.manager.tag
+                default: master-head
+                type: string
+              - variable: image.longhorn.engine.tag
+                default: 'master-head'
+            - variable: persistence.defaultClass
+              default: \"true\"
+        "};
+
+        let data = vec![
+            (
+                values,
+                "image.longhorn.engine.tag",
+                "tag: v1.6.0 # engine tag",
+            ),
+            (values, "image.longhorn.manager.tag", "tag: \"v1.6.0\""),
+            (
+                questions,
+                "questions[variable=image.defaultImage].subquestions[variable=image.longhorn.engine.tag].default",
+                "default: 'v1.6.0'",
+            ),
+            (questions, "questions[0].subquestions[0].default", "default: v1.6.0"),
+        ];
+
+        for (content, path, expected_line) in data {
+            assert_eq!(get_yaml_path(content, path)?, "master-head");
+
+            let updated = set_yaml_path(content, path, "v1.6.0")?;
+            let changed: Vec<(&str, &str)> = content
+                .lines()
+                .zip(updated.lines())
+                .filter(|(a, b)| a != b)
+                .collect();
+
+            assert_eq!(changed.len(), 1, "{path}");
+            assert_eq!(changed[0].1.trim(), expected_line);
+        }
+
+        assert_eq!(get_yaml_path(values, "image.csi.attacher.tag")?, "v4.4.2");
+        assert_eq!(get_yaml_path(questions, "categories[0]")?, "storage");
+        assert!(get_yaml_path(values, "image.longhorn.ui.tag").is_err());
+        assert!(get_yaml_path(values, "image.longhorn").is_err());
+
+        Ok(())
+    }
+}